@@ -1,23 +1,37 @@
 //! CLI testing harness for hip-key
 //!
-//! Supports Telex and VNI input methods
+//! Supports the Telex variants, VNI, VIQR and accent-less typing, converts files between
+//! Unicode and the legacy encodings, and regenerates the dictionary
 
 use std::env;
+use std::fs;
 use std::io::{self, Write};
-use hip_key_lang_vi::{Vietnamese, InputMethod};
+use std::process;
+use hip_key_core::{Engine, EngineEvent, Key, Keystroke};
+use hip_key_lang_vi::{Dictionary, Encoding, Vietnamese, InputMethod};
 
 fn print_help(method: InputMethod) {
     println!("hip-key CLI Testing Harness");
     println!("============================");
     println!();
-    if method == InputMethod::Telex {
-        println!("Input method: Telex");
+    println!("Input method: {}", method.name());
+    if method.is_telex() {
         println!("  aw  → ă    aa  → â    ow  → ơ    oo  → ô");
         println!("  uw  → ư    dd  → đ    ee  → ê");
-        println!("  as  → á    af  → à    aj  → ả    ar  → ạ");
-        println!("  ax  → a (remove tone)");
+        println!("  as  → á    af  → à    ar  → ả    ax  → ã    aj  → ạ");
+        println!("  asz → a (remove tone)");
+        if method == InputMethod::Telex2 {
+            println!("  w   → ư    [   → ơ    ]   → ư");
+        }
+    } else if method == InputMethod::Viqr {
+        println!("  a(  → ă    a^  → â    o+  → ơ    o^  → ô");
+        println!("  u+  → ư    dd  → đ    e^  → ê");
+        println!("  a'  → á    a`  → à    a?  → ả    a~  → ã    a.  → ạ");
+        println!("  \\?  → ? (literal mark)");
+    } else if method == InputMethod::Accentless {
+        println!("  viet nam → candidates Việt Nam, việt năm, ...");
+        println!("  type without marks; the accented words are listed");
     } else {
-        println!("Input method: VNI");
         println!("  a8  → ă    a6  → â    o7  → ơ    o6  → ô");
         println!("  u7  → ư    d9  → đ    e6  → ê");
         println!("  a1  → á    a2  → à    a3  → ả    a4  → ã    a5  → ạ");
@@ -25,19 +39,103 @@ fn print_help(method: InputMethod) {
     println!();
     println!("Commands:");
     println!("  q  → quit");
-    println!("  m  → switch input method (Telex/VNI)");
+    println!("  m  → switch input method (Telex/Simple Telex/Telex 2/VNI/VIQR/Accent-less)");
     println!();
 }
 
+/// Type `input` key by key into an engine, as a platform adapter would
+///
+/// Returns the text and the candidates offered for its end.
+fn convert(method: InputMethod, input: &str) -> (String, Vec<String>) {
+    let mut engine = Engine::new();
+    engine.set_language_pack(Box::new(Vietnamese::with_method(method)));
+    let mut output = String::new();
+    for c in input.chars() {
+        let keystroke = if c == ' ' { Keystroke::key(Key::Space) } else { Keystroke::char(c) };
+        for event in engine.process(&keystroke) {
+            match event {
+                EngineEvent::Commit(text) => output.push_str(&text),
+                // The host would insert the key itself
+                EngineEvent::PassThrough => output.push(c),
+                EngineEvent::BufferChanged | EngineEvent::CandidatesUpdated => {}
+            }
+        }
+    }
+    let candidates = engine.candidates().iter().map(|candidate| candidate.text.clone()).collect();
+    output.push_str(&engine.commit());
+    (output, candidates)
+}
+
+/// The method after `method` in `InputMethod::ALL`, wrapping around
+fn next_method(method: InputMethod) -> InputMethod {
+    let index = InputMethod::ALL.iter().position(|&m| m == method).unwrap_or(0);
+    InputMethod::ALL[(index + 1) % InputMethod::ALL.len()]
+}
+
+fn print_usage() {
+    eprintln!("Usage: hip-key [METHOD]");
+    eprintln!("       hip-key encode <ENCODING> <INPUT> <OUTPUT>   (Unicode → legacy)");
+    eprintln!("       hip-key decode <ENCODING> <INPUT> <OUTPUT>   (legacy → Unicode)");
+    eprintln!("       hip-key dict <WORD-LIST> <OUTPUT>            (build a dictionary file)");
+    eprintln!();
+    let ids: Vec<&str> = Encoding::ALL.iter().map(|encoding| encoding.id()).collect();
+    eprintln!("Encodings: {}", ids.join(", "));
+}
+
+/// Convert the file `input` into `output` in the direction of `command`
+fn convert_file(command: &str, encoding: &str, input: &str, output: &str) -> Result<(), String> {
+    let encoding = Encoding::from_id(&encoding.to_ascii_lowercase())
+        .ok_or_else(|| format!("unknown encoding: {}", encoding))?;
+    let bytes = fs::read(input).map_err(|e| format!("{}: {}", input, e))?;
+    let converted = if command == "encode" {
+        let text = String::from_utf8(bytes).map_err(|_| format!("{}: not UTF-8", input))?;
+        encoding.encode(&text)
+    } else {
+        encoding.decode(&bytes).into_bytes()
+    };
+    fs::write(output, converted).map_err(|e| format!("{}: {}", output, e))
+}
+
+/// Write the dictionary file for the word list `input` to `output`
+fn build_dictionary(input: &str, output: &str) -> Result<(), String> {
+    let list = fs::read_to_string(input).map_err(|e| format!("{}: {}", input, e))?;
+    fs::write(output, Dictionary::from_word_list(&list).to_file()).map_err(|e| format!("{}: {}", output, e))
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    // File conversion: hip-key encode|decode <encoding> <input> <output>
+    if let Some(command @ ("encode" | "decode")) = args.get(1).map(String::as_str) {
+        let [encoding, input, output] = &args[2..] else {
+            print_usage();
+            process::exit(2);
+        };
+        if let Err(message) = convert_file(command, encoding, input, output) {
+            eprintln!("hip-key: {}", message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Dictionary: hip-key dict <word-list> <output>
+    if args.get(1).is_some_and(|command| command == "dict") {
+        let [input, output] = &args[2..] else {
+            print_usage();
+            process::exit(2);
+        };
+        if let Err(message) = build_dictionary(input, output) {
+            eprintln!("hip-key: {}", message);
+            process::exit(1);
+        }
+        return;
+    }
+
     // Check for method in command line args
-    let mut method = if args.len() > 1 && args[1] == "vni" {
-        InputMethod::VNI
-    } else {
-        InputMethod::Telex
-    };
+    let mut method = args
+        .get(1)
+        .and_then(|id| InputMethod::from_id(&id.to_ascii_lowercase()))
+        .unwrap_or_default();
 
     print_help(method);
 
@@ -45,8 +143,7 @@ fn main() {
     let mut stdout = io::stdout();
 
     loop {
-        let method_name = if method == InputMethod::Telex { "Telex" } else { "VNI" };
-        print!("[{}] > ", method_name);
+        print!("[{}] > ", method.name());
         stdout.flush().expect("Failed to flush stdout");
 
         let mut input = String::new();
@@ -59,11 +156,7 @@ fn main() {
         }
 
         if input == "m" {
-            method = if method == InputMethod::Telex {
-                InputMethod::VNI
-            } else {
-                InputMethod::Telex
-            };
+            method = next_method(method);
             println!();
             print_help(method);
             continue;
@@ -73,13 +166,11 @@ fn main() {
             continue;
         }
 
-        // Convert based on current method
-        let vi = Vietnamese::with_method(method);
-        let result = if method == InputMethod::Telex {
-            vi.convert_telex(input)
-        } else {
-            vi.convert_vni(input)
-        };
-        println!("   → {}\n", result);
+        let (output, candidates) = convert(method, input);
+        println!("   → {}", output);
+        if !candidates.is_empty() {
+            println!("     candidates: {}", candidates.iter().take(5).cloned().collect::<Vec<_>>().join(", "));
+        }
+        println!();
     }
 }
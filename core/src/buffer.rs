@@ -10,7 +10,9 @@ pub struct Buffer {
     committed: String,
     /// Text currently being composed
     composing: String,
-    /// Cursor position within composing text
+    /// Characters typed for the current composition, before interpretation
+    raw: String,
+    /// Cursor position within composing text (byte offset)
     cursor: usize,
 }
 
@@ -25,6 +27,7 @@ impl Buffer {
         Self {
             committed: String::new(),
             composing: String::new(),
+            raw: String::new(),
             cursor: 0,
         }
     }
@@ -45,6 +48,16 @@ impl Buffer {
         self.cursor = self.composing.len();
     }
 
+    /// Get the raw characters typed for the current composition
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Record a typed character
+    pub fn push_raw(&mut self, ch: char) {
+        self.raw.push(ch);
+    }
+
     /// Get cursor position in composing text
     pub fn cursor(&self) -> usize {
         self.cursor
@@ -57,10 +70,14 @@ impl Buffer {
     }
 
     /// Delete character before cursor (backspace)
+    ///
+    /// Editing the composition by hand makes the raw keys meaningless, so
+    /// they are reset to the remaining composing text.
     pub fn backspace(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-            self.composing.remove(self.cursor);
+        if let Some((pos, _)) = self.composing[..self.cursor].char_indices().next_back() {
+            self.composing.remove(pos);
+            self.cursor = pos;
+            self.raw = self.composing.clone();
         }
     }
 
@@ -68,33 +85,37 @@ impl Buffer {
     pub fn delete(&mut self) {
         if self.cursor < self.composing.len() {
             self.composing.remove(self.cursor);
+            self.raw = self.composing.clone();
         }
     }
 
-    /// Move cursor
+    /// Move cursor, snapping back to a character boundary
     pub fn move_cursor(&mut self, pos: usize) {
-        self.cursor = pos.min(self.composing.len());
+        let mut pos = pos.min(self.composing.len());
+        while !self.composing.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        self.cursor = pos;
     }
 
     /// Commit composing text
     pub fn commit(&mut self) {
         if !self.composing.is_empty() {
             self.committed.push_str(&self.composing);
-            self.composing.clear();
-            self.cursor = 0;
+            self.clear();
         }
     }
 
     /// Commit with specific text
     pub fn commit_with(&mut self, text: &str) {
         self.committed.push_str(text);
-        self.composing.clear();
-        self.cursor = 0;
+        self.clear();
     }
 
     /// Clear composing text without committing
     pub fn clear(&mut self) {
         self.composing.clear();
+        self.raw.clear();
         self.cursor = 0;
     }
 
@@ -136,6 +157,34 @@ mod tests {
         assert_eq!(buf.composing(), "a");
     }
 
+    #[test]
+    fn test_buffer_backspace_multibyte() {
+        let mut buf = Buffer::new();
+        buf.set_composing("viêt");
+        buf.backspace();
+        assert_eq!(buf.composing(), "viê");
+        buf.backspace();
+        assert_eq!(buf.composing(), "vi");
+        assert_eq!(buf.raw(), "vi");
+
+        buf.set_composing("ơ");
+        buf.move_cursor(1);
+        assert_eq!(buf.cursor(), 0);
+        buf.delete();
+        assert_eq!(buf.composing(), "");
+    }
+
+    #[test]
+    fn test_buffer_raw() {
+        let mut buf = Buffer::new();
+        buf.push_raw('a');
+        buf.push_raw('a');
+        buf.set_composing("â");
+        assert_eq!(buf.raw(), "aa");
+        buf.commit();
+        assert_eq!(buf.raw(), "");
+    }
+
     #[test]
     fn test_buffer_commit() {
         let mut buf = Buffer::new();
@@ -1,5 +1,42 @@
 //! Candidate word/phrase suggestions
 
+/// Where a candidate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CandidateSource {
+    /// The language pack's bundled dictionary
+    #[default]
+    Dictionary,
+    /// Words the user added or the pack learned
+    UserDictionary,
+    /// A text expansion macro
+    Macro,
+    /// Text the user committed before
+    History,
+}
+
+/// What a candidate is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CandidateKind {
+    /// A single word
+    #[default]
+    Word,
+    /// Several words
+    Phrase,
+    /// An emoji or other symbol
+    Emoji,
+    /// A fix for what was typed
+    Correction,
+}
+
+/// What the user did with a candidate, reported back to the language pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    /// The candidate was chosen
+    Accepted,
+    /// The candidate was dismissed or undone
+    Rejected,
+}
+
 /// A candidate suggestion for completion/composition
 ///
 /// Produced by language packs, consumed by UI layer.
@@ -11,19 +48,71 @@ pub struct Candidate {
     pub annotation: Option<String>,
     /// Confidence score (0.0 to 1.0)
     pub confidence: f32,
+    /// Where the candidate came from
+    pub source: CandidateSource,
+    /// What the candidate is
+    pub kind: CandidateKind,
+    /// Stable identifier, for feedback (see [`Candidate::stable_id`])
+    pub id: u64,
+    /// Whether `id` was given with `with_id` rather than derived
+    custom_id: bool,
 }
 
 impl Candidate {
     pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
         Self {
-            text: text.into(),
+            id: Self::stable_id(&text, None),
+            text,
             annotation: None,
             confidence: 0.5,
+            source: CandidateSource::default(),
+            kind: CandidateKind::default(),
+            custom_id: false,
+        }
+    }
+
+    /// Identifier derived from the text and annotation
+    ///
+    /// Equal candidates get equal ids, and ids are the same in every run,
+    /// so hosts may keep them: FNV-1a over the UTF-8 text, a NUL, then the
+    /// annotation.
+    pub fn stable_id(text: &str, annotation: Option<&str>) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let bytes = text.bytes().chain(annotation.map(|a| std::iter::once(0).chain(a.bytes())).into_iter().flatten());
+        for byte in bytes {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
         }
+        hash
     }
 
+    /// Set the annotation, updating the id unless one was given with `with_id`
     pub fn with_annotation(mut self, annotation: impl Into<String>) -> Self {
-        self.annotation = Some(annotation.into());
+        let annotation = annotation.into();
+        if !self.custom_id {
+            self.id = Self::stable_id(&self.text, Some(&annotation));
+        }
+        self.annotation = Some(annotation);
+        self
+    }
+
+    pub fn with_source(mut self, source: CandidateSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: CandidateKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Use an id of the provider's own (a user dictionary row, a macro)
+    ///
+    /// It is kept through later builder calls.
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+        self.custom_id = true;
         self
     }
 
@@ -53,7 +142,8 @@ impl From<&str> for Candidate {
 impl PartialEq for Candidate {
     fn eq(&self, other: &Self) -> bool {
         self.text == other.text && self.annotation == other.annotation
-        // Note: confidence is intentionally excluded from comparison
+        // Note: confidence, source, kind and id are intentionally excluded
+        // from comparison, so the same text from two sources is one candidate
     }
 }
 
@@ -84,6 +174,34 @@ mod tests {
         assert_eq!(c.confidence, 0.9);
     }
 
+    #[test]
+    fn test_candidate_metadata() {
+        let c = Candidate::new("xin chào");
+        assert_eq!((c.source, c.kind), (CandidateSource::Dictionary, CandidateKind::Word));
+
+        let c = c.with_source(CandidateSource::History).with_kind(CandidateKind::Phrase);
+        assert_eq!((c.source, c.kind), (CandidateSource::History, CandidateKind::Phrase));
+        assert_eq!(c, Candidate::new("xin chào"));
+        assert_eq!(Candidate::new("x").with_id(7).id, 7);
+    }
+
+    #[test]
+    fn test_candidate_stable_id() {
+        // Fixed across runs and builds
+        assert_eq!(Candidate::stable_id("", None), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Candidate::new("a").id, 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(Candidate::new("việt").id, Candidate::new("việt").with_confidence(0.9).id);
+        assert_ne!(Candidate::new("việt").id, Candidate::new("viết").id);
+
+        let annotated = Candidate::new("a").with_annotation("b");
+        assert_eq!(annotated.id, Candidate::stable_id("a", Some("b")));
+        assert_ne!(annotated.id, Candidate::new("ab").id);
+
+        // An id given by hand survives annotating, in either order
+        assert_eq!(Candidate::new("a").with_id(7).with_annotation("b").id, 7);
+        assert_eq!(Candidate::new("a").with_annotation("b").with_id(7).id, 7);
+    }
+
     #[test]
     fn test_candidate_confidence_clamp() {
         let c = Candidate::new("test").with_confidence(1.5);
@@ -1,9 +1,16 @@
 //! Core input method engine
 
+use std::ops::Range;
+use std::sync::Arc;
+
 use crate::buffer::Buffer;
-use crate::keystroke::Keystroke;
+use crate::keystroke::{ArrowDirection, Key, Keystroke};
 use crate::langpack::{LanguagePack, ProcessResult};
-use crate::candidate::CandidateList;
+use crate::candidate::{Candidate, CandidateList, Feedback};
+use crate::ranking::RankingPipeline;
+
+/// Candidates shown per page unless set otherwise, one per digit key
+const DEFAULT_PAGE_SIZE: usize = 9;
 
 /// Main input method engine
 ///
@@ -12,10 +19,23 @@ use crate::candidate::CandidateList;
 /// - Route keystrokes to language pack
 /// - Handle commit/undo
 /// - NOT: interpret keystrokes (language pack's job)
+///
+/// Language packs are shared: many engines (one per input context) can use
+/// the same loaded pack.
 pub struct Engine {
     buffer: Buffer,
-    lang_pack: Option<Box<dyn LanguagePack>>,
+    lang_pack: Option<Arc<dyn LanguagePack>>,
     candidates: CandidateList,
+    /// The list shown before `candidates`, for feedback after a selection
+    previous_candidates: CandidateList,
+    /// Index of the highlighted candidate
+    highlighted: usize,
+    /// Whether `candidates` holds a list the language pack answered with
+    pack_candidates: bool,
+    /// Whether selection keys also work lists only the providers offered
+    selection_keys: bool,
+    page_size: usize,
+    ranking: RankingPipeline,
 }
 
 impl Engine {
@@ -24,76 +44,278 @@ impl Engine {
             buffer: Buffer::new(),
             lang_pack: None,
             candidates: Vec::new(),
+            previous_candidates: Vec::new(),
+            highlighted: 0,
+            pack_candidates: false,
+            selection_keys: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            ranking: RankingPipeline::new(),
         }
     }
 
     /// Load a language pack
     pub fn set_language_pack(&mut self, pack: Box<dyn LanguagePack>) {
+        self.lang_pack = Some(Arc::from(pack));
+    }
+
+    /// Use a language pack shared with other engines
+    pub fn set_shared_language_pack(&mut self, pack: Arc<dyn LanguagePack>) {
         self.lang_pack = Some(pack);
     }
 
+    /// Get the current language pack, for sharing with other engines
+    pub fn language_pack(&self) -> Option<&Arc<dyn LanguagePack>> {
+        self.lang_pack.as_ref()
+    }
+
+    /// Merge and rank candidates with `ranking` before showing them
+    ///
+    /// Its providers are asked whenever the composition changes, and their
+    /// candidates merged with the language pack's.
+    pub fn set_ranking(&mut self, ranking: RankingPipeline) {
+        self.ranking = ranking;
+    }
+
+    /// Get the ranking pipeline
+    pub fn ranking(&self) -> &RankingPipeline {
+        &self.ranking
+    }
+
+    /// Let selection keys work candidates that only the providers offered
+    ///
+    /// Off by default: such candidates are only shown, and keys reach the
+    /// language pack as if there were none. Even when on, a digit the pack
+    /// transforms the composition with (a VNI tone) goes to the pack.
+    pub fn set_selection_keys(&mut self, enabled: bool) {
+        self.selection_keys = enabled;
+    }
+
+    /// Whether selection keys work candidates only the providers offered
+    pub fn selection_keys(&self) -> bool {
+        self.selection_keys
+    }
+
     /// Get current language pack info
     pub fn language_pack_id(&self) -> Option<&str> {
         self.lang_pack.as_ref().map(|p| p.id())
     }
 
     /// Process a keystroke through the engine
-    pub fn process(&mut self, keystroke: &Keystroke) -> EngineEvent {
-        // Handle terminators first
-        if keystroke.is_terminator() {
-            return EngineEvent::Commit(self.buffer.composing().to_string());
+    ///
+    /// Returns the events for the host, in the order they must be applied.
+    /// With text being composed:
+    /// - Escape abandons the composition and commits the raw keys as typed
+    /// - Space, Tab, Enter and arrows commit (through the language pack's
+    ///   commit path), then pass the key through
+    /// - Backspace/Delete edit the composition
+    ///
+    /// Without text being composed, these keys pass straight through.
+    /// The buffer is reset after every commit, so `Commit` is always
+    /// followed by `BufferChanged` (and then any `PassThrough`).
+    ///
+    /// A pack answering with candidates consumes a typed character as with
+    /// `Consumed`; answering Space that way keeps composing, with the space
+    /// added to the composition (`viet nam`). Candidates are dropped when
+    /// the composition changes without new ones.
+    ///
+    /// While candidates the pack answered with are shown (or any, with
+    /// [`set_selection_keys`](Self::set_selection_keys)), some keys work
+    /// the list instead:
+    /// - digits 1-9 select that candidate of the current page
+    /// - Enter selects the highlighted candidate; Escape is left to commit
+    ///   the raw keys as typed
+    /// - Tab/Right and Shift+Tab/Left move the highlight
+    /// - Down and Up turn the page
+    pub fn process(&mut self, keystroke: &Keystroke) -> Vec<EngineEvent> {
+        let Some(pack) = self.lang_pack.clone() else {
+            // No language pack: simple passthrough
+            return vec![EngineEvent::PassThrough];
+        };
+        // Packs and the buffer see letters in the case they were typed
+        let keystroke = &keystroke.resolved();
+
+        if let Some(events) = self.navigate(keystroke) {
+            return events;
         }
 
-        // Handle deletions directly
-        if keystroke.is_deletion() {
+        if keystroke.is_terminator() || keystroke.is_deletion() {
+            if self.is_idle() {
+                return vec![EngineEvent::PassThrough];
+            }
             match keystroke.key {
-                crate::keystroke::Key::Backspace => self.buffer.backspace(),
-                crate::keystroke::Key::Delete => self.buffer.delete(),
-                _ => {}
+                Key::Escape => {
+                    let raw = self.buffer.raw().to_string();
+                    return self.commit_text(raw);
+                }
+                Key::Backspace => {
+                    self.buffer.backspace();
+                    return self.buffer_changed();
+                }
+                Key::Delete => {
+                    self.buffer.delete();
+                    return self.buffer_changed();
+                }
+                _ => {
+                    // Let the pack finalize the word, then hand the key on
+                    let text = match pack.process_with_raw(keystroke, self.buffer.composing(), self.buffer.raw()) {
+                        ProcessResult::ReadyToCommit(text) => text,
+                        ProcessResult::Candidates(candidates) => {
+                            // Still composing: a space joins the next word
+                            if keystroke.key == Key::Space {
+                                self.buffer.push_raw(' ');
+                                self.buffer.append(' ');
+                            }
+                            self.show_pack_candidates(candidates);
+                            return vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated];
+                        }
+                        _ => self.buffer.composing().to_string(),
+                    };
+                    let mut events = self.commit_text(text);
+                    events.push(EngineEvent::PassThrough);
+                    return events;
+                }
             }
-            return EngineEvent::BufferChanged;
         }
 
-        // Route to language pack if available
-        if let Some(pack) = &self.lang_pack {
-            let result = pack.process(keystroke, self.buffer.composing());
+        let result = pack.process_with_raw(keystroke, self.buffer.composing(), self.buffer.raw());
+        // A digit the pack had no use for selects from the providers' list
+        let modifiers = keystroke.modifiers;
+        let plain = !(modifiers.ctrl || modifiers.alt || modifiers.meta);
+        if self.selection_keys && plain && !matches!(result, ProcessResult::BufferUpdated(_)) {
+            if let Some(index) = self.digit_index(keystroke) {
+                return self.select_candidate(index);
+            }
+        }
+        if let Key::Char(c) = keystroke.key {
+            if matches!(result, ProcessResult::Consumed | ProcessResult::BufferUpdated(_) | ProcessResult::Candidates(_)) {
+                self.buffer.push_raw(c);
+            }
+        }
 
-            match result {
-                ProcessResult::BufferUpdated(new_buffer) => {
-                    // Language pack provided new buffer content
-                    self.buffer.set_composing(&new_buffer);
-                    EngineEvent::BufferChanged
-                }
-                ProcessResult::Consumed => {
-                    // Language pack handled it, append the keystroke
-                    if let crate::keystroke::Key::Char(c) = keystroke.key {
-                        self.buffer.append(c);
-                    }
-                    EngineEvent::BufferChanged
-                }
-                ProcessResult::PassThrough => {
-                    // Let the keystroke through as-is
-                    EngineEvent::PassThrough
-                }
-                ProcessResult::Candidates(candidates) => {
-                    self.candidates = candidates;
-                    EngineEvent::CandidatesUpdated
+        match result {
+            ProcessResult::BufferUpdated(new_buffer) => {
+                // Language pack provided new buffer content
+                self.buffer.set_composing(&new_buffer);
+                self.buffer_changed()
+            }
+            ProcessResult::Consumed => {
+                // Language pack handled it, append the keystroke
+                if let Key::Char(c) = keystroke.key {
+                    self.buffer.append(c);
                 }
-                ProcessResult::ReadyToCommit(text) => {
-                    self.buffer.commit_with(&text);
-                    EngineEvent::Commit(text)
+                self.buffer_changed()
+            }
+            ProcessResult::PassThrough => {
+                // Let the keystroke through as-is
+                vec![EngineEvent::PassThrough]
+            }
+            ProcessResult::Candidates(candidates) => {
+                // Consumed like `Consumed`, with candidates for the result
+                if let Key::Char(c) = keystroke.key {
+                    self.buffer.append(c);
                 }
+                self.show_pack_candidates(candidates);
+                vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]
             }
+            ProcessResult::ReadyToCommit(text) => self.commit_text(text),
+        }
+    }
+
+    /// Handle a key that works the candidate list, if one is shown
+    ///
+    /// Lists only the providers offered are display-only unless selection
+    /// keys are on.
+    fn navigate(&mut self, keystroke: &Keystroke) -> Option<Vec<EngineEvent>> {
+        let modifiers = keystroke.modifiers;
+        let selectable = self.pack_candidates || self.selection_keys;
+        if self.candidates.is_empty() || !selectable || modifiers.ctrl || modifiers.alt || modifiers.meta {
+            return None;
+        }
+        let events = match keystroke.key {
+            // Digits for the providers' list are left to `process`, so the
+            // pack's own use of one (a tone mark) comes first
+            Key::Char('1'..='9') if self.pack_candidates => self.select_candidate(self.digit_index(keystroke)?),
+            Key::Enter => self.select_candidate(self.highlighted),
+            Key::Tab if modifiers.shift => self.highlight_previous(),
+            Key::Tab | Key::Arrow(ArrowDirection::Right) => self.highlight_next(),
+            Key::Arrow(ArrowDirection::Left) => self.highlight_previous(),
+            Key::Arrow(ArrowDirection::Down) => self.next_page(),
+            Key::Arrow(ArrowDirection::Up) => self.previous_page(),
+            _ => return None,
+        };
+        Some(events)
+    }
+
+    /// The candidate of the current page a digit key picks
+    fn digit_index(&self, keystroke: &Keystroke) -> Option<usize> {
+        let Key::Char(c @ '1'..='9') = keystroke.key else {
+            return None;
+        };
+        let index = self.page_range().start + c as usize - '1' as usize;
+        self.page_range().contains(&index).then_some(index)
+    }
+
+    /// Replace the candidates, highlighting the first
+    fn show_candidates(&mut self, candidates: CandidateList) {
+        let shown = std::mem::replace(&mut self.candidates, candidates);
+        if !shown.is_empty() {
+            self.previous_candidates = shown;
+        }
+        self.highlighted = 0;
+        self.pack_candidates = false;
+    }
+
+    /// Show the pack's `candidates` merged with the providers' by the ranking
+    fn show_ranked(&mut self, candidates: CandidateList) {
+        let ranked = self.ranking.rank(candidates, self.buffer.composing());
+        self.show_candidates(ranked);
+    }
+
+    /// Show the list the pack answered with, which selection keys work
+    fn show_pack_candidates(&mut self, candidates: CandidateList) {
+        self.show_ranked(candidates);
+        self.pack_candidates = true;
+    }
+
+    /// Move the highlight to `index`, turning the page as needed
+    fn highlight(&mut self, index: usize) -> Vec<EngineEvent> {
+        if index == self.highlighted || index >= self.candidates.len() {
+            return Vec::new();
+        }
+        self.highlighted = index;
+        vec![EngineEvent::CandidatesUpdated]
+    }
+
+    /// Events for a composition change, dropping candidates made for the old one
+    ///
+    /// The ranking's providers may offer new ones, for display only.
+    fn buffer_changed(&mut self) -> Vec<EngineEvent> {
+        if self.candidates.is_empty() && !self.ranking.has_providers() {
+            return vec![EngineEvent::BufferChanged];
+        }
+        let had_candidates = !self.candidates.is_empty();
+        self.show_ranked(Vec::new());
+        if had_candidates || !self.candidates.is_empty() {
+            vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]
         } else {
-            // No language pack: simple passthrough
-            EngineEvent::PassThrough
+            vec![EngineEvent::BufferChanged]
         }
     }
 
+    /// Commit `text` in place of the composition and reset composing state
+    ///
+    /// The composition is then empty, so `BufferChanged` follows the commit.
+    fn commit_text(&mut self, text: String) -> Vec<EngineEvent> {
+        self.buffer.commit_with(&text);
+        self.show_candidates(Vec::new());
+        vec![EngineEvent::Commit(text), EngineEvent::BufferChanged]
+    }
+
     /// Commit current composition
     pub fn commit(&mut self) -> String {
         let text = self.buffer.composing().to_string();
         self.buffer.commit();
+        self.show_candidates(Vec::new());
         text
     }
 
@@ -103,14 +325,104 @@ impl Engine {
     }
 
     /// Get current candidates
-    pub fn candidates(&self) -> &[crate::candidate::Candidate] {
+    pub fn candidates(&self) -> &[Candidate] {
         &self.candidates
     }
 
+    /// Index of the highlighted candidate, if there are any
+    pub fn highlighted(&self) -> Option<usize> {
+        (!self.candidates.is_empty()).then_some(self.highlighted)
+    }
+
+    /// Set how many candidates make a page (at least 1)
+    ///
+    /// Digit keys reach the first nine of a page.
+    pub fn set_page_size(&mut self, size: usize) {
+        self.page_size = size.max(1);
+    }
+
+    /// Get how many candidates make a page
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Index of the page holding the highlighted candidate
+    pub fn page(&self) -> usize {
+        self.highlighted / self.page_size
+    }
+
+    /// Number of pages of candidates
+    pub fn page_count(&self) -> usize {
+        self.candidates.len().div_ceil(self.page_size)
+    }
+
+    /// Indices in [`candidates`](Self::candidates) of the current page
+    pub fn page_range(&self) -> Range<usize> {
+        let start = self.page() * self.page_size;
+        start..self.candidates.len().min(start + self.page_size)
+    }
+
+    /// Commit the candidate at `index` in place of the composition
+    ///
+    /// Returns no events if there is no such candidate.
+    pub fn select_candidate(&mut self, index: usize) -> Vec<EngineEvent> {
+        match self.candidates.get(index) {
+            Some(candidate) => {
+                let text = candidate.text.clone();
+                self.commit_text(text)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Pass feedback on the candidate with `id` to the language pack
+    ///
+    /// The candidate is looked up among the current candidates, then the
+    /// list shown before them, so feedback can follow a selection. Returns
+    /// whether it was found.
+    pub fn candidate_feedback(&self, id: u64, feedback: Feedback) -> bool {
+        let Some(pack) = &self.lang_pack else {
+            return false;
+        };
+        let found = self.candidates.iter().chain(&self.previous_candidates).find(|candidate| candidate.id == id);
+        if let Some(candidate) = found {
+            pack.candidate_feedback(candidate, feedback);
+        }
+        found.is_some()
+    }
+
+    /// Highlight the next candidate, turning the page after its last
+    ///
+    /// Returns `CandidatesUpdated` if the highlight moved.
+    pub fn highlight_next(&mut self) -> Vec<EngineEvent> {
+        self.highlight(self.highlighted + 1)
+    }
+
+    /// Highlight the previous candidate, turning back a page before its first
+    pub fn highlight_previous(&mut self) -> Vec<EngineEvent> {
+        match self.highlighted.checked_sub(1) {
+            Some(index) => self.highlight(index),
+            None => Vec::new(),
+        }
+    }
+
+    /// Turn to the next page, highlighting its first candidate
+    pub fn next_page(&mut self) -> Vec<EngineEvent> {
+        self.highlight((self.page() + 1) * self.page_size)
+    }
+
+    /// Turn to the previous page, highlighting its first candidate
+    pub fn previous_page(&mut self) -> Vec<EngineEvent> {
+        match self.page().checked_sub(1) {
+            Some(page) => self.highlight(page * self.page_size),
+            None => Vec::new(),
+        }
+    }
+
     /// Clear composition state
     pub fn clear(&mut self) {
         self.buffer.clear();
-        self.candidates.clear();
+        self.show_candidates(Vec::new());
     }
 
     /// Check if engine is idle (no active composition)
@@ -132,7 +444,7 @@ impl Default for Engine {
 pub enum EngineEvent {
     /// Buffer composition changed
     BufferChanged,
-    /// Candidates list updated
+    /// Candidates list, or which one is highlighted, updated
     CandidatesUpdated,
     /// Text ready to commit
     Commit(String),
@@ -143,17 +455,82 @@ pub enum EngineEvent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::candidate::{Candidate, CandidateSource};
+    use crate::ranking::SourceWeights;
 
-    struct TestLanguagePack;
+    type ProcessFn = dyn Fn(&Keystroke, &str, &str) -> ProcessResult + Send + Sync;
 
-    impl LanguagePack for TestLanguagePack {
-        fn process(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
-            if let crate::keystroke::Key::Char(c) = keystroke.key {
-                if buffer == "a" && c == 'w' {
-                    return ProcessResult::ReadyToCommit(String::from("ă"));
+    /// Pack that handles keystrokes with a closure over the key, the
+    /// composition and the raw keys, and records the feedback it gets
+    struct TestPack {
+        process: Box<ProcessFn>,
+        feedback: std::sync::Mutex<Vec<(String, Feedback)>>,
+    }
+
+    impl TestPack {
+        fn new(process: impl Fn(&Keystroke, &str, &str) -> ProcessResult + Send + Sync + 'static) -> Self {
+            Self { process: Box::new(process), feedback: Default::default() }
+        }
+
+        /// Commits "ă" on 'w' after "a" and consumes everything else
+        fn breve() -> Self {
+            Self::new(|keystroke, buffer, _raw| match keystroke.key {
+                Key::Char('w') if buffer == "a" => ProcessResult::ReadyToCommit(String::from("ă")),
+                _ => ProcessResult::Consumed,
+            })
+        }
+
+        /// Turns "aa" into "â" and commits on Space with a marker
+        fn circumflex() -> Self {
+            Self::new(|keystroke, buffer, _raw| match keystroke.key {
+                Key::Char('a') if buffer.ends_with('a') => {
+                    ProcessResult::BufferUpdated(format!("{}â", &buffer[..buffer.len() - 1]))
                 }
-            }
-            ProcessResult::Consumed
+                Key::Char(_) => ProcessResult::Consumed,
+                Key::Space => ProcessResult::ReadyToCommit(format!("{}!", buffer)),
+                _ => ProcessResult::PassThrough,
+            })
+        }
+
+        /// Like `circumflex`, but commits the raw keys too on Space
+        fn raw() -> Self {
+            let circumflex = Self::circumflex();
+            Self::new(move |keystroke, buffer, raw| match keystroke.key {
+                Key::Space => ProcessResult::ReadyToCommit(format!("{}|{}", buffer, raw)),
+                _ => (circumflex.process)(keystroke, buffer, raw),
+            })
+        }
+
+        /// Suggests the composition in capitals, keeping words apart on Space
+        fn suggest() -> Self {
+            Self::new(|keystroke, buffer, _raw| {
+                let next = match (keystroke.key, keystroke.typed_char()) {
+                    (Key::Space, _) => format!("{} ", buffer),
+                    (_, Some(c)) => format!("{}{}", buffer, c),
+                    _ => return ProcessResult::ReadyToCommit(buffer.to_string()),
+                };
+                ProcessResult::Candidates(vec![Candidate::new(next.to_uppercase())])
+            })
+        }
+
+        /// Offers twelve numbered candidates for any composition
+        fn list() -> Self {
+            Self::new(|keystroke, buffer, _raw| match keystroke.typed_char() {
+                Some(c) => ProcessResult::Candidates(
+                    (0..12).map(|i| Candidate::new(format!("{}{}{}", buffer, c, i))).collect(),
+                ),
+                None => ProcessResult::ReadyToCommit(buffer.to_string()),
+            })
+        }
+    }
+
+    impl LanguagePack for TestPack {
+        fn process(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
+            (self.process)(keystroke, buffer, buffer)
+        }
+
+        fn process_with_raw(&self, keystroke: &Keystroke, buffer: &str, raw: &str) -> ProcessResult {
+            (self.process)(keystroke, buffer, raw)
         }
 
         fn generate_candidates(&self, _buffer: &str) -> CandidateList {
@@ -171,6 +548,10 @@ mod tests {
         fn name(&self) -> &str {
             "Test"
         }
+
+        fn candidate_feedback(&self, candidate: &Candidate, feedback: Feedback) {
+            self.feedback.lock().unwrap().push((candidate.text.clone(), feedback));
+        }
     }
 
     #[test]
@@ -183,14 +564,37 @@ mod tests {
     #[test]
     fn test_engine_set_language_pack() {
         let mut engine = Engine::new();
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
         assert_eq!(engine.language_pack_id(), Some("test"));
     }
 
+    #[test]
+    fn test_engine_shared_language_pack() {
+        let pack: Arc<dyn LanguagePack> = Arc::new(TestPack::breve());
+        let mut first = Engine::new();
+        let mut second = Engine::new();
+        first.set_shared_language_pack(Arc::clone(&pack));
+        second.set_shared_language_pack(Arc::clone(&pack));
+        assert_eq!(Arc::strong_count(&pack), 3);
+
+        // Sessions share the pack but not the composition
+        let _ = first.process(&Keystroke::char('a'));
+        let _ = second.process(&Keystroke::char('b'));
+        assert_eq!(first.buffer().composing(), "a");
+        assert_eq!(second.buffer().composing(), "b");
+        assert!(Arc::ptr_eq(first.language_pack().unwrap(), &pack));
+    }
+
+    #[test]
+    fn test_engine_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Engine>();
+    }
+
     #[test]
     fn test_engine_backspace() {
         let mut engine = Engine::new();
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
 
         // Type "ab"
         let _ = engine.process(&Keystroke::char('a'));
@@ -205,22 +609,22 @@ mod tests {
     #[test]
     fn test_engine_commit_ready() {
         let mut engine = Engine::new();
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
 
         // Type 'a'
         let _ = engine.process(&Keystroke::char('a'));
         assert_eq!(engine.buffer().composing(), "a");
 
         // Type 'w' -> should trigger "ă" commit
-        let event = engine.process(&Keystroke::char('w'));
-        assert_eq!(event, EngineEvent::Commit(String::from("ă")));
+        let events = engine.process(&Keystroke::char('w'));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("ă")), EngineEvent::BufferChanged]);
         assert_eq!(engine.buffer().committed(), "ă");
     }
 
     #[test]
     fn test_engine_clear() {
         let mut engine = Engine::new();
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
 
         let _ = engine.process(&Keystroke::char('a'));
         assert!(!engine.is_idle());
@@ -235,12 +639,12 @@ mod tests {
         let mut engine = Engine::new();
 
         // No language pack - pass through
-        let event = engine.process(&Keystroke::char('a'));
-        assert_eq!(event, EngineEvent::PassThrough);
+        let events = engine.process(&Keystroke::char('a'));
+        assert_eq!(events, vec![EngineEvent::PassThrough]);
         assert_eq!(engine.buffer().composing(), "");
 
         // With language pack - characters append
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
         let _ = engine.process(&Keystroke::char('x'));
         assert_eq!(engine.buffer().composing(), "x");
         let _ = engine.process(&Keystroke::char('i'));
@@ -252,7 +656,7 @@ mod tests {
     #[test]
     fn test_engine_backspace_with_buffer() {
         let mut engine = Engine::new();
-        engine.set_language_pack(Box::new(TestLanguagePack));
+        engine.set_language_pack(Box::new(TestPack::breve()));
 
         // Type "abc"
         let _ = engine.process(&Keystroke::char('a'));
@@ -270,4 +674,299 @@ mod tests {
         let _ = engine.process(&Keystroke::char('b'));
         assert_eq!(engine.buffer().composing(), "ab");
     }
+
+    fn engine_with(pack: impl LanguagePack + 'static, text: &str) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_language_pack(Box::new(pack));
+        for c in text.chars() {
+            let _ = engine.process(&Keystroke::char(c));
+        }
+        engine
+    }
+
+    #[test]
+    fn test_engine_escape_restores_raw_keys() {
+        let mut engine = engine_with(TestPack::circumflex(), "baa");
+        assert_eq!(engine.buffer().composing(), "bâ");
+        assert_eq!(engine.buffer().raw(), "baa");
+
+        let events = engine.process(&Keystroke::key(Key::Escape));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("baa")), EngineEvent::BufferChanged]);
+        assert!(engine.is_idle());
+        assert_eq!(engine.buffer().raw(), "");
+
+        // Nothing composing: Escape belongs to the host
+        let events = engine.process(&Keystroke::key(Key::Escape));
+        assert_eq!(events, vec![EngineEvent::PassThrough]);
+    }
+
+    #[test]
+    fn test_engine_space_commits_through_pack() {
+        let mut engine = engine_with(TestPack::circumflex(), "baa");
+        let events = engine.process(&Keystroke::key(Key::Space));
+        assert_eq!(
+            events,
+            vec![EngineEvent::Commit(String::from("bâ!")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+        assert!(engine.is_idle());
+        assert_eq!(engine.buffer().committed(), "bâ!");
+
+        let events = engine.process(&Keystroke::key(Key::Space));
+        assert_eq!(events, vec![EngineEvent::PassThrough]);
+    }
+
+    #[test]
+    fn test_engine_gives_pack_raw_keys() {
+        let mut engine = engine_with(TestPack::raw(), "baa");
+        let events = engine.process(&Keystroke::key(Key::Space));
+        assert_eq!(
+            events,
+            vec![EngineEvent::Commit(String::from("bâ|baa")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+    }
+
+    #[test]
+    fn test_engine_enter_and_tab_commit_then_pass_through() {
+        for key in [Key::Enter, Key::Tab] {
+            // The pack passes these through, so the engine commits as-is
+            let mut engine = engine_with(TestPack::circumflex(), "aa");
+            let events = engine.process(&Keystroke::key(key));
+            assert_eq!(
+                events,
+                vec![EngineEvent::Commit(String::from("â")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+            );
+            assert!(engine.is_idle());
+        }
+    }
+
+    #[test]
+    fn test_engine_applies_shift_and_caps_lock() {
+        let mut engine = engine_with(TestPack::circumflex(), "");
+        let shifted = |shift, caps_lock| Keystroke {
+            key: Key::Char('b'),
+            modifiers: crate::keystroke::Modifiers { shift, caps_lock, ..Default::default() },
+        };
+        let _ = engine.process(&shifted(true, false));
+        let _ = engine.process(&shifted(false, true));
+        let _ = engine.process(&shifted(true, true));
+        assert_eq!(engine.buffer().composing(), "BBb");
+        assert_eq!(engine.buffer().raw(), "BBb");
+    }
+
+    #[test]
+    fn test_engine_backspace_when_idle_passes_through() {
+        let mut engine = engine_with(TestPack::circumflex(), "");
+        let events = engine.process(&Keystroke::backspace());
+        assert_eq!(events, vec![EngineEvent::PassThrough]);
+
+        let mut engine = engine_with(TestPack::circumflex(), "aa");
+        let events = engine.process(&Keystroke::backspace());
+        assert_eq!(events, vec![EngineEvent::BufferChanged]);
+        assert!(engine.is_idle());
+    }
+
+    #[test]
+    fn test_engine_candidates_from_pack() {
+        let mut engine = engine_with(TestPack::suggest(), "ab");
+        assert_eq!(engine.buffer().composing(), "ab");
+        assert_eq!(engine.candidates(), [Candidate::new("AB")]);
+
+        // Space keeps composing
+        let events = engine.process(&Keystroke::key(Key::Space));
+        assert_eq!(events, vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]);
+        let _ = engine.process(&Keystroke::char('c'));
+        assert_eq!(engine.buffer().composing(), "ab c");
+        assert_eq!(engine.buffer().raw(), "ab c");
+        assert_eq!(engine.candidates(), [Candidate::new("AB C")]);
+
+        // Editing drops candidates made for the old composition
+        let events = engine.process(&Keystroke::backspace());
+        assert_eq!(events, vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]);
+        assert!(engine.candidates().is_empty());
+
+        let events = engine.process(&Keystroke::key(Key::Enter));
+        assert_eq!(
+            events,
+            vec![EngineEvent::Commit(String::from("ab ")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+    }
+
+    #[test]
+    fn test_engine_candidate_paging() {
+        let mut engine = engine_with(TestPack::list(), "a");
+        engine.set_page_size(5);
+        assert_eq!(engine.highlighted(), Some(0));
+        assert_eq!((engine.page(), engine.page_count(), engine.page_range()), (0, 3, 0..5));
+
+        // The highlight turns the page at its end, and back
+        for _ in 0..5 {
+            assert_eq!(engine.process(&Keystroke::key(Key::Tab)), vec![EngineEvent::CandidatesUpdated]);
+        }
+        assert_eq!((engine.highlighted(), engine.page()), (Some(5), 1));
+        let _ = engine.process(&Keystroke::key(Key::Arrow(ArrowDirection::Left)));
+        assert_eq!((engine.highlighted(), engine.page()), (Some(4), 0));
+
+        // Pages stop at either end
+        let down = Keystroke::key(Key::Arrow(ArrowDirection::Down));
+        let _ = engine.process(&down);
+        let _ = engine.process(&down);
+        assert_eq!((engine.highlighted(), engine.page_range()), (Some(10), 10..12));
+        assert!(engine.process(&down).is_empty());
+        assert!(engine.highlight_next().len() == 1 && engine.highlight_next().is_empty());
+        let _ = engine.previous_page();
+        assert_eq!(engine.highlighted(), Some(5));
+
+        // New candidates start over on the first
+        let _ = engine.process(&Keystroke::char('b'));
+        assert_eq!((engine.highlighted(), engine.page()), (Some(0), 0));
+        assert!(engine.previous_page().is_empty() && engine.highlight_previous().is_empty());
+    }
+
+    #[test]
+    fn test_engine_candidate_selection() {
+        // A digit picks from the current page
+        let mut engine = engine_with(TestPack::list(), "a");
+        engine.set_page_size(5);
+        let _ = engine.next_page();
+        let events = engine.process(&Keystroke::char('2'));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("a6")), EngineEvent::BufferChanged]);
+        assert!(engine.is_idle() && engine.candidates().is_empty());
+        assert_eq!(engine.highlighted(), None);
+
+        // Digits past the page go to the pack
+        let mut engine = engine_with(TestPack::list(), "a");
+        engine.set_page_size(2);
+        let _ = engine.process(&Keystroke::char('3'));
+        assert_eq!(engine.buffer().composing(), "a3");
+
+        // Enter takes the highlighted candidate
+        let mut engine = engine_with(TestPack::list(), "a");
+        let _ = engine.process(&Keystroke::key(Key::Arrow(ArrowDirection::Right)));
+        let events = engine.process(&Keystroke::key(Key::Enter));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("a1")), EngineEvent::BufferChanged]);
+
+        // Escape commits the keys as typed instead
+        let mut engine = engine_with(TestPack::list(), "ab");
+        let _ = engine.process(&Keystroke::key(Key::Arrow(ArrowDirection::Right)));
+        let events = engine.process(&Keystroke::key(Key::Escape));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("ab")), EngineEvent::BufferChanged]);
+        assert!(engine.candidates().is_empty());
+
+        assert!(engine.select_candidate(0).is_empty());
+        let mut engine = engine_with(TestPack::list(), "a");
+        assert_eq!(
+            engine.select_candidate(11),
+            vec![EngineEvent::Commit(String::from("a11")), EngineEvent::BufferChanged]
+        );
+
+        // Without candidates, arrows still commit and pass through
+        let mut engine = engine_with(TestPack::circumflex(), "ba");
+        let events = engine.process(&Keystroke::key(Key::Arrow(ArrowDirection::Down)));
+        assert_eq!(
+            events,
+            vec![EngineEvent::Commit(String::from("ba")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+    }
+
+    #[test]
+    fn test_engine_candidate_feedback() {
+        let pack = Arc::new(TestPack::list());
+        let mut engine = Engine::new();
+        engine.set_shared_language_pack(pack.clone());
+        assert!(!engine.candidate_feedback(0, Feedback::Accepted));
+
+        let _ = engine.process(&Keystroke::char('a'));
+        let rejected = engine.candidates()[1].id;
+        assert!(engine.candidate_feedback(rejected, Feedback::Rejected));
+
+        // Still known once selecting has cleared the list
+        let accepted = engine.candidates()[0].id;
+        let _ = engine.select_candidate(0);
+        assert!(engine.candidate_feedback(accepted, Feedback::Accepted));
+        assert!(!engine.candidate_feedback(Candidate::stable_id("zz", None), Feedback::Accepted));
+
+        let feedback = pack.feedback.lock().unwrap();
+        assert_eq!(*feedback, [(String::from("a1"), Feedback::Rejected), (String::from("a0"), Feedback::Accepted)]);
+    }
+
+    /// Provider offering words that start with the composition
+    struct WordProvider;
+
+    impl crate::ranking::CandidateProvider for WordProvider {
+        fn candidates(&self, composing: &str) -> CandidateList {
+            ["ab", "abc", "b1"]
+                .into_iter()
+                .filter(|word| word.starts_with(composing))
+                .map(|word| Candidate::new(word).with_source(CandidateSource::History).with_confidence(0.4))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_engine_ranks_candidates() {
+        let mut engine = Engine::new();
+        let weights = SourceWeights::new().with(CandidateSource::History, 2.0);
+        engine.set_ranking(RankingPipeline::new().with_provider(WordProvider).with_weighting(weights).with_limit(3));
+        engine.set_language_pack(Box::new(TestPack::list()));
+        assert_eq!(engine.ranking().limit(), 3);
+
+        // Merged with the pack's, weighted, ranked and capped
+        let _ = engine.process(&Keystroke::char('a'));
+        let texts: Vec<&str> = engine.candidates().iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["ab", "abc", "a0"]);
+        assert_eq!(engine.candidates()[0].source, CandidateSource::History);
+
+        // Providers also offer candidates for packs that give none
+        let mut engine = Engine::new();
+        engine.set_ranking(RankingPipeline::new().with_provider(WordProvider));
+        engine.set_language_pack(Box::new(TestPack::breve()));
+        let events = engine.process(&Keystroke::char('a'));
+        assert_eq!(events, vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]);
+        assert_eq!(engine.candidates(), [Candidate::new("ab"), Candidate::new("abc")]);
+        let events = engine.process(&Keystroke::char('x'));
+        assert_eq!(events, vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]);
+        assert!(engine.candidates().is_empty());
+        assert_eq!(engine.process(&Keystroke::char('y')), vec![EngineEvent::BufferChanged]);
+
+        // Those are only shown, unless selection keys are on
+        let mut engine = Engine::new();
+        engine.set_ranking(RankingPipeline::new().with_provider(WordProvider));
+        engine.set_language_pack(Box::new(TestPack::breve()));
+        assert!(!engine.selection_keys());
+        let _ = engine.process(&Keystroke::char('a'));
+        let events = engine.process(&Keystroke::key(Key::Enter));
+        assert_eq!(
+            events,
+            vec![EngineEvent::Commit(String::from("a")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+        engine.set_selection_keys(true);
+        let _ = engine.process(&Keystroke::char('a'));
+        assert_eq!(
+            engine.process(&Keystroke::char('2')),
+            vec![EngineEvent::Commit(String::from("abc")), EngineEvent::BufferChanged]
+        );
+
+        // The pack sees each digit once, and its own use of one comes first
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        let pack = TestPack::new(move |keystroke, buffer, _raw| {
+            counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            match keystroke.key {
+                Key::Char('1') => ProcessResult::BufferUpdated(format!("{}!", buffer)),
+                _ => ProcessResult::Consumed,
+            }
+        });
+        let mut engine = Engine::new();
+        engine.set_ranking(RankingPipeline::new().with_provider(WordProvider));
+        engine.set_language_pack(Box::new(pack));
+        engine.set_selection_keys(true);
+        let _ = engine.process(&Keystroke::char('a'));
+        let events = engine.process(&Keystroke::char('1'));
+        assert_eq!(events, vec![EngineEvent::BufferChanged, EngineEvent::CandidatesUpdated]);
+        assert_eq!(engine.buffer().composing(), "a!");
+        let _ = engine.process(&Keystroke::key(Key::Backspace));
+        let events = engine.process(&Keystroke::char('2'));
+        assert_eq!(events, vec![EngineEvent::Commit(String::from("abc")), EngineEvent::BufferChanged]);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 3);
+    }
 }
@@ -52,6 +52,7 @@ pub struct Modifiers {
     pub ctrl: bool,
     pub alt: bool,
     pub meta: bool, // Command/Windows key
+    pub caps_lock: bool,
 }
 
 impl Keystroke {
@@ -71,11 +72,47 @@ impl Keystroke {
         }
     }
 
+    /// Create a non-character keystroke without modifiers
+    pub fn key(key: Key) -> Self {
+        Self {
+            key,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// The character typed, with Shift and Caps Lock applied to letters
+    ///
+    /// Hosts may send either the resolved character or the unshifted one;
+    /// a lowercase letter is uppercased when exactly one of Shift and
+    /// Caps Lock is active, and anything else is taken as already resolved.
+    pub fn typed_char(&self) -> Option<char> {
+        match self.key {
+            Key::Char(c) if c.is_lowercase() && self.modifiers.shift != self.modifiers.caps_lock => {
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(u), None) => Some(u),
+                    // No single-char uppercase form (e.g. ß): keep as typed
+                    _ => Some(c),
+                }
+            }
+            Key::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// This keystroke with Shift and Caps Lock applied to its character
+    pub fn resolved(&self) -> Self {
+        match self.typed_char() {
+            Some(c) => Self { key: Key::Char(c), ..*self },
+            None => *self,
+        }
+    }
+
     /// Check if this keystroke should terminate composition
     pub fn is_terminator(&self) -> bool {
         matches!(
             self.key,
-            Key::Enter | Key::Escape | Key::Arrow(_)
+            Key::Enter | Key::Escape | Key::Space | Key::Tab | Key::Arrow(_)
         )
     }
 
@@ -122,6 +159,25 @@ mod tests {
     fn test_is_terminator() {
         assert!(Keystroke { key: Key::Enter, modifiers: Modifiers::default() }.is_terminator());
         assert!(Keystroke { key: Key::Escape, modifiers: Modifiers::default() }.is_terminator());
+        assert!(Keystroke::key(Key::Space).is_terminator());
+        assert!(Keystroke::key(Key::Tab).is_terminator());
         assert!(!Keystroke::char('a').is_terminator());
     }
+
+    #[test]
+    fn test_typed_char_case() {
+        let with = |c, shift, caps_lock| Keystroke {
+            key: Key::Char(c),
+            modifiers: Modifiers { shift, caps_lock, ..Modifiers::default() },
+        };
+        assert_eq!(with('a', false, false).typed_char(), Some('a'));
+        assert_eq!(with('a', true, false).typed_char(), Some('A'));
+        assert_eq!(with('a', false, true).typed_char(), Some('A'));
+        assert_eq!(with('a', true, true).typed_char(), Some('a'));
+        // Already resolved by the host
+        assert_eq!(with('A', true, false).typed_char(), Some('A'));
+        assert_eq!(with('1', true, false).typed_char(), Some('1'));
+        assert_eq!(with('a', true, false).resolved().key, Key::Char('A'));
+        assert_eq!(Keystroke::key(Key::Space).typed_char(), None);
+    }
 }
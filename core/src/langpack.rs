@@ -6,7 +6,7 @@
 //! - Optional context/ranking
 
 use crate::keystroke::Keystroke;
-use crate::candidate::CandidateList;
+use crate::candidate::{Candidate, CandidateList, Feedback};
 
 /// Result of processing a keystroke
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,6 +18,10 @@ pub enum ProcessResult {
     /// Buffer content updated (language pack provides new buffer content)
     BufferUpdated(String),
     /// Candidate list updated
+    ///
+    /// A typed character is consumed into the composition as with
+    /// `Consumed`. Given for Space, Tab, Enter or an arrow, the composition
+    /// continues instead of committing, and Space is added to it.
     Candidates(CandidateList),
     /// Composition ready to commit
     ReadyToCommit(String),
@@ -37,6 +41,16 @@ pub trait LanguagePack: Send + Sync {
     /// - Should we commit?
     fn process(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult;
 
+    /// Process a keystroke, also given the raw keys typed for the composition
+    ///
+    /// This is what the engine calls. Packs that can fall back to what was
+    /// typed (e.g. when a word turns out not to be in their language)
+    /// override it; the default ignores `raw` and calls `process`.
+    fn process_with_raw(&self, keystroke: &Keystroke, buffer: &str, raw: &str) -> ProcessResult {
+        let _ = raw;
+        self.process(keystroke, buffer)
+    }
+
     /// Generate candidates for current buffer
     ///
     /// Called explicitly (e.g., user presses suggestion key).
@@ -51,6 +65,11 @@ pub trait LanguagePack: Send + Sync {
     /// Get language pack display name
     fn name(&self) -> &str;
 
+    /// Learn from what the user did with a candidate it offered
+    ///
+    /// The default ignores feedback.
+    fn candidate_feedback(&self, _candidate: &Candidate, _feedback: Feedback) {}
+
     /// Optional: Get version
     fn version(&self) -> &str {
         "0.1.0"
@@ -93,5 +112,6 @@ mod tests {
         let pack = DummyLanguagePack;
         assert_eq!(pack.id(), "dummy");
         assert_eq!(pack.name(), "Dummy");
+        assert_eq!(pack.process_with_raw(&Keystroke::char('a'), "", ""), ProcessResult::PassThrough);
     }
 }
@@ -8,6 +8,7 @@ pub mod keystroke;
 pub mod buffer;
 pub mod candidate;
 pub mod langpack;
+pub mod ranking;
 
 // Core engine entry point
 pub use engine::{Engine, EngineEvent};
@@ -15,5 +16,6 @@ pub use engine::{Engine, EngineEvent};
 // Common types for convenience
 pub use keystroke::{Keystroke, Key, Modifiers};
 pub use buffer::Buffer;
-pub use candidate::{Candidate, CandidateList};
+pub use candidate::{Candidate, CandidateKind, CandidateList, CandidateSource, Feedback};
 pub use langpack::{LanguagePack, ProcessResult, DynLanguagePack};
+pub use ranking::{CandidateProvider, RankingPipeline, SourceWeights, Weighting};
@@ -0,0 +1,243 @@
+//! Candidate merging and ranking
+//!
+//! Candidates can come from several places: the language pack, and extra
+//! providers such as user history or macros. `RankingPipeline` merges their
+//! lists into the one list the engine shows.
+
+use std::sync::Arc;
+
+use crate::candidate::{Candidate, CandidateList, CandidateSource};
+
+/// Most candidates a pipeline keeps unless set otherwise
+const DEFAULT_LIMIT: usize = 20;
+
+/// A source of candidates besides the language pack
+pub trait CandidateProvider: Send + Sync {
+    /// Candidates for the composing text, which is never empty
+    fn candidates(&self, composing: &str) -> CandidateList;
+}
+
+/// How much a candidate counts when lists are merged
+///
+/// Any `Fn(&Candidate) -> f32` is a weighting.
+pub trait Weighting: Send + Sync {
+    /// Score of `candidate` in one list; the scores of duplicates add up
+    fn weight(&self, candidate: &Candidate) -> f32;
+}
+
+impl<F: Fn(&Candidate) -> f32 + Send + Sync> Weighting for F {
+    fn weight(&self, candidate: &Candidate) -> f32 {
+        self(candidate)
+    }
+}
+
+/// Weighting that scales confidence by a factor per source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceWeights {
+    /// One factor per `CandidateSource`, in declaration order
+    factors: [f32; 4],
+}
+
+impl SourceWeights {
+    /// Every source counts the same
+    pub fn new() -> Self {
+        Self { factors: [1.0; 4] }
+    }
+
+    /// Scale the confidence of candidates from `source` by `factor`
+    pub fn with(mut self, source: CandidateSource, factor: f32) -> Self {
+        self.factors[source as usize] = factor;
+        self
+    }
+
+    /// Get the factor for `source`
+    pub fn factor(&self, source: CandidateSource) -> f32 {
+        self.factors[source as usize]
+    }
+}
+
+impl Default for SourceWeights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Weighting for SourceWeights {
+    fn weight(&self, candidate: &Candidate) -> f32 {
+        candidate.confidence * self.factor(candidate.source)
+    }
+}
+
+/// Merges candidate lists, removes duplicates, ranks and caps them
+///
+/// Duplicates are equal `Candidate`s (same text and annotation); the first
+/// one seen is kept, with the weights of all of them added up. The merged
+/// list is ordered by that score, which becomes each candidate's
+/// confidence (capped at 1.0).
+pub struct RankingPipeline {
+    providers: Vec<Arc<dyn CandidateProvider>>,
+    weighting: Box<dyn Weighting>,
+    limit: usize,
+}
+
+impl RankingPipeline {
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            weighting: Box::new(SourceWeights::new()),
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// Also ask `provider` for candidates, after the language pack
+    pub fn with_provider(mut self, provider: impl CandidateProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Use a provider shared with other pipelines
+    pub fn with_shared_provider(mut self, provider: Arc<dyn CandidateProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Score candidates with `weighting` (default: [`SourceWeights::new`])
+    pub fn with_weighting(mut self, weighting: impl Weighting + 'static) -> Self {
+        self.weighting = Box::new(weighting);
+        self
+    }
+
+    /// Keep at most `limit` candidates (default 20)
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Get the most candidates kept
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Whether any providers are set
+    pub fn has_providers(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    /// Merge `lists` into one ranked, capped list
+    pub fn merge(&self, lists: impl IntoIterator<Item = CandidateList>) -> CandidateList {
+        let mut merged: Vec<(Candidate, f32)> = Vec::new();
+        for candidate in lists.into_iter().flatten() {
+            let weight = self.weighting.weight(&candidate);
+            match merged.iter_mut().find(|(seen, _)| *seen == candidate) {
+                Some((_, score)) => *score += weight,
+                None => merged.push((candidate, weight)),
+            }
+        }
+        // Stable, so equal scores keep the order they came in
+        merged.sort_by(|a, b| b.1.total_cmp(&a.1));
+        merged
+            .into_iter()
+            .take(self.limit)
+            .map(|(candidate, score)| candidate.with_confidence(score))
+            .collect()
+    }
+
+    /// Rank the language pack's candidates with the providers' for `composing`
+    pub fn rank(&self, pack_candidates: CandidateList, composing: &str) -> CandidateList {
+        if composing.is_empty() {
+            return self.merge([pack_candidates]);
+        }
+        let provided = self.providers.iter().map(|provider| provider.candidates(composing));
+        self.merge(std::iter::once(pack_candidates).chain(provided))
+    }
+}
+
+impl Default for RankingPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candidate::CandidateKind;
+
+    /// Provider that remembers a few committed words
+    struct History(Vec<&'static str>);
+
+    impl CandidateProvider for History {
+        fn candidates(&self, composing: &str) -> CandidateList {
+            self.0
+                .iter()
+                .filter(|word| word.starts_with(composing))
+                .map(|&word| Candidate::new(word).with_source(CandidateSource::History).with_confidence(0.5))
+                .collect()
+        }
+    }
+
+    fn texts(candidates: &[Candidate]) -> Vec<&str> {
+        candidates.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_merge_removes_duplicates() {
+        let pipeline = RankingPipeline::new();
+        let pack = vec![Candidate::new("a").with_confidence(0.5), Candidate::new("b").with_confidence(0.4)];
+        let other = vec![
+            Candidate::new("b").with_confidence(0.3).with_kind(CandidateKind::Correction),
+            Candidate::new("b").with_annotation("note").with_confidence(0.2),
+        ];
+        let merged = pipeline.merge([pack, other]);
+
+        // The two plain `b`s add up and overtake `a`; the annotated one is distinct
+        assert_eq!(texts(&merged), ["b", "a", "b"]);
+        assert!((merged[0].confidence - 0.7).abs() < 1e-6);
+        assert_eq!(merged[0].kind, CandidateKind::Word);
+        assert_eq!(merged[2].annotation.as_deref(), Some("note"));
+    }
+
+    #[test]
+    fn test_weighting() {
+        let pack = || vec![Candidate::new("a").with_confidence(0.6)];
+        let history = || vec![Candidate::new("b").with_source(CandidateSource::History).with_confidence(0.4)];
+
+        let even = RankingPipeline::new();
+        assert_eq!(texts(&even.merge([pack(), history()])), ["a", "b"]);
+
+        let weights = SourceWeights::new().with(CandidateSource::History, 2.0);
+        assert_eq!(weights.factor(CandidateSource::Dictionary), 1.0);
+        let favour_history = RankingPipeline::new().with_weighting(weights);
+        assert_eq!(texts(&favour_history.merge([pack(), history()])), ["b", "a"]);
+
+        // Any closure will do
+        let shortest = RankingPipeline::new().with_weighting(|c: &Candidate| 1.0 / c.text.len() as f32);
+        let merged = shortest.merge([vec![Candidate::new("long"), Candidate::new("ab")]]);
+        assert_eq!(texts(&merged), ["ab", "long"]);
+        assert_eq!(merged[0].confidence, 0.5);
+    }
+
+    #[test]
+    fn test_limit() {
+        let many: CandidateList = (0..30).map(|i| Candidate::new(i.to_string())).collect();
+        assert_eq!(RankingPipeline::new().merge([many.clone()]).len(), DEFAULT_LIMIT);
+        let capped = RankingPipeline::new().with_limit(3);
+        assert_eq!(texts(&capped.merge([many])), ["0", "1", "2"]);
+        assert_eq!(capped.limit(), 3);
+    }
+
+    #[test]
+    fn test_rank_with_providers() {
+        let pipeline = RankingPipeline::new().with_provider(History(vec!["việt", "viết", "hoa"]));
+        assert!(pipeline.has_providers());
+        let pack = vec![Candidate::new("việt").with_confidence(0.6), Candidate::new("viên").with_confidence(0.3)];
+
+        let ranked = pipeline.rank(pack.clone(), "vi");
+        assert_eq!(texts(&ranked), ["việt", "viết", "viên"]);
+        assert_eq!(ranked[0].source, CandidateSource::Dictionary);
+        assert_eq!(ranked[1].source, CandidateSource::History);
+
+        // Providers are not asked without composing text
+        assert_eq!(texts(&pipeline.rank(pack, "")), ["việt", "viên"]);
+    }
+}
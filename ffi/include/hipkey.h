@@ -0,0 +1,437 @@
+/* hip-key C API. Generated by cbindgen from ffi/src/lib.rs; do not edit. */
+
+#ifndef HIPKEY_H
+#define HIPKEY_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// ABI major version: bumped on any incompatible change to the C API
+#define HIPKEY_ABI_VERSION_MAJOR 1
+
+// ABI minor version: bumped when functions or constants are added
+#define HIPKEY_ABI_VERSION_MINOR 4
+
+// Modifier bits for `HipKeyKeystroke::modifiers`
+#define HIPKEY_MOD_SHIFT (1 << 0)
+
+#define HIPKEY_MOD_CTRL (1 << 1)
+
+#define HIPKEY_MOD_ALT (1 << 2)
+
+#define HIPKEY_MOD_META (1 << 3)
+
+// Caps Lock is on; lowercase letters are then typed uppercase
+#define HIPKEY_MOD_CAPS_LOCK (1 << 4)
+
+// Event bits reported by `hipkey_process_keystroke`
+//
+// When both are set, the commit happens before the pass-through. A commit
+// always empties the preedit, so it comes with `HIPKEY_EVENT_BUFFER_CHANGED`.
+#define HIPKEY_EVENT_BUFFER_CHANGED (1 << 0)
+
+#define HIPKEY_EVENT_CANDIDATES_UPDATED (1 << 1)
+
+#define HIPKEY_EVENT_COMMIT (1 << 2)
+
+#define HIPKEY_EVENT_PASS_THROUGH (1 << 3)
+
+// Result codes for FFI operations
+typedef enum HipKeyResult {
+  HIP_KEY_RESULT_SUCCESS = 0,
+  HIP_KEY_RESULT_ERROR = -1,
+  HIP_KEY_RESULT_INVALID_ARGUMENT = -2,
+  HIP_KEY_RESULT_NOT_READY = -3,
+  // Output buffer too small; the required length was still reported
+  HIP_KEY_RESULT_BUFFER_TOO_SMALL = -4,
+  // Called from inside one of the engine's callbacks, which cannot modify it
+  HIP_KEY_RESULT_BUSY = -5,
+} HipKeyResult;
+
+// Key kinds accepted in `HipKeyKeystroke::key`
+typedef enum HipKeyKey {
+  HIP_KEY_KEY_CHAR = 0,
+  HIP_KEY_KEY_BACKSPACE = 1,
+  HIP_KEY_KEY_DELETE = 2,
+  HIP_KEY_KEY_ENTER = 3,
+  HIP_KEY_KEY_ESCAPE = 4,
+  HIP_KEY_KEY_TAB = 5,
+  HIP_KEY_KEY_SPACE = 6,
+  HIP_KEY_KEY_ARROW_UP = 7,
+  HIP_KEY_KEY_ARROW_DOWN = 8,
+  HIP_KEY_KEY_ARROW_LEFT = 9,
+  HIP_KEY_KEY_ARROW_RIGHT = 10,
+  HIP_KEY_KEY_UNKNOWN = 11,
+} HipKeyKey;
+
+// Values of `HipKeyCandidateInfo::source`
+typedef enum HipKeyCandidateSource {
+  HIP_KEY_CANDIDATE_SOURCE_DICTIONARY = 0,
+  HIP_KEY_CANDIDATE_SOURCE_USER_DICTIONARY = 1,
+  HIP_KEY_CANDIDATE_SOURCE_MACRO = 2,
+  HIP_KEY_CANDIDATE_SOURCE_HISTORY = 3,
+} HipKeyCandidateSource;
+
+// Values of `HipKeyCandidateInfo::kind`
+typedef enum HipKeyCandidateKind {
+  HIP_KEY_CANDIDATE_KIND_WORD = 0,
+  HIP_KEY_CANDIDATE_KIND_PHRASE = 1,
+  HIP_KEY_CANDIDATE_KIND_EMOJI = 2,
+  HIP_KEY_CANDIDATE_KIND_CORRECTION = 3,
+} HipKeyCandidateKind;
+
+// Opaque handle to Engine instance
+//
+// Deliberately not `repr(C)`: C only ever sees a forward declaration.
+typedef struct HipKeyEngine HipKeyEngine;
+
+// Opaque handle to a session registry
+typedef struct HipKeyRegistry HipKeyRegistry;
+
+// A keystroke as seen by the C API
+//
+// `key` is a `HipKeyKey` value. `code` is the Unicode scalar value for
+// `Char`, the platform key code for `Unknown`, and ignored otherwise.
+// `modifiers` is a combination of `HIPKEY_MOD_*` bits.
+typedef struct HipKeyKeystroke {
+  uint32_t key;
+  uint32_t code;
+  uint32_t modifiers;
+} HipKeyKeystroke;
+
+// Event callbacks for an engine
+//
+// Any callback may be null. Text arguments are NUL-terminated UTF-8 of
+// `len` bytes. Pointers passed to a callback are only valid for the
+// duration of that call.
+typedef struct HipKeyCallbacks {
+  // Passed back unchanged as the first argument of every callback
+  void *user_data;
+  // Composing text changed
+  void (*on_preedit_changed)(void *user_data, const char *text, size_t len);
+  // Candidate list changed; `count` is the new number of candidates
+  void (*on_candidates_updated)(void *user_data, size_t count);
+  // Text to insert into the document
+  void (*on_commit)(void *user_data, const char *text, size_t len);
+  // Keystroke not consumed by the engine, for the host to handle itself
+  void (*on_pass_through)(void *user_data, const struct HipKeyKeystroke *keystroke);
+} HipKeyCallbacks;
+
+// Metadata of a candidate, as seen by the C API
+//
+// `id` stays the same for the same candidate across calls and runs; pass
+// it to `hipkey_candidate_feedback`. `source` is a `HipKeyCandidateSource`
+// value and `kind` a `HipKeyCandidateKind` value.
+typedef struct HipKeyCandidateInfo {
+  uint64_t id;
+  uint32_t source;
+  uint32_t kind;
+  float confidence;
+} HipKeyCandidateInfo;
+
+// Identifier of a session within its registry; never 0
+typedef uint64_t HipKeySessionId;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// ABI version of the loaded library, encoded as `(major << 16) | minor`
+//
+// Adapters should refuse to run if the major version differs from the
+// `HIPKEY_ABI_VERSION_MAJOR` they were compiled against.
+uint32_t hipkey_abi_version(void);
+
+// Create a new engine with no language pack loaded
+//
+// Returns null on failure. Release with `hipkey_engine_destroy`.
+struct HipKeyEngine *hipkey_engine_create(void);
+
+// Destroy an engine created by `hipkey_engine_create`
+//
+// Ignored when called from inside one of the engine's callbacks. No other
+// thread may be using the engine.
+//
+// # Safety
+// `engine` must be null or a pointer returned by `hipkey_engine_create`
+// that has not been destroyed yet.
+void hipkey_engine_destroy(struct HipKeyEngine *engine);
+
+// Register event callbacks, replacing any previous set
+//
+// Pass null to remove callbacks. The struct is copied; `user_data` must stay
+// valid until the callbacks are replaced or the engine is destroyed.
+//
+// # Safety
+// `engine` must be a live engine pointer; `callbacks` must be null or point
+// to a valid `HipKeyCallbacks` whose function pointers are safe to call.
+enum HipKeyResult hipkey_engine_set_callbacks(struct HipKeyEngine *engine,
+                                              const struct HipKeyCallbacks *callbacks);
+
+// Load a language pack by id (e.g. `"vi"`) and method (e.g. `"telex"`,
+// `"simple-telex"`, `"telex2"`, `"vni"`, `"viqr"`, `"accentless"`)
+//
+// `method` may be null to use the pack's default.
+//
+// # Safety
+// `engine` must be a live engine pointer; `id` and `method` must be null or
+// NUL-terminated strings.
+enum HipKeyResult hipkey_engine_set_language_pack(struct HipKeyEngine *engine,
+                                                  const char *id,
+                                                  const char *method);
+
+// Process a keystroke
+//
+// `out_events`, if not null, receives a combination of `HIPKEY_EVENT_*` bits.
+// After `HIPKEY_EVENT_COMMIT`, read the text with `hipkey_get_last_commit_text`.
+// When both `HIPKEY_EVENT_COMMIT` and `HIPKEY_EVENT_PASS_THROUGH` are set
+// (Space, Tab, Enter, arrows), insert the commit before forwarding the key.
+// Registered callbacks fire before this returns; `on_commit` is always
+// followed by `on_preedit_changed` with empty text, as with `hipkey_commit`.
+// Returns `NotReady` if no language pack is loaded.
+//
+// # Safety
+// `engine` must be a live engine pointer; `keystroke` must point to a valid
+// `HipKeyKeystroke`; `out_events` must be null or valid for a write.
+enum HipKeyResult hipkey_process_keystroke(struct HipKeyEngine *engine,
+                                           const struct HipKeyKeystroke *keystroke,
+                                           uint32_t *out_events);
+
+// Copy the current composing (preedit) text
+//
+// # Safety
+// `engine` must be a live engine pointer; `buf` must be null or valid for
+// `capacity` bytes; `out_len` must be null or valid for a write.
+enum HipKeyResult hipkey_get_composing_text(struct HipKeyEngine *engine,
+                                            char *buf,
+                                            size_t capacity,
+                                            size_t *out_len);
+
+// Copy all text committed so far in this engine
+//
+// # Safety
+// Same as `hipkey_get_composing_text`.
+enum HipKeyResult hipkey_get_committed_text(struct HipKeyEngine *engine,
+                                            char *buf,
+                                            size_t capacity,
+                                            size_t *out_len);
+
+// Copy the text of the most recent commit
+//
+// # Safety
+// Same as `hipkey_get_composing_text`.
+enum HipKeyResult hipkey_get_last_commit_text(struct HipKeyEngine *engine,
+                                              char *buf,
+                                              size_t capacity,
+                                              size_t *out_len);
+
+// Commit the current composition
+//
+// The committed text is available through `hipkey_get_last_commit_text`
+// and is also delivered to `on_commit`, followed by the emptied preedit.
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum HipKeyResult hipkey_commit(struct HipKeyEngine *engine);
+
+// Drop the current composition and candidates without committing
+//
+// Delivers the emptied preedit to `on_preedit_changed`.
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum HipKeyResult hipkey_clear(struct HipKeyEngine *engine);
+
+// Report whether a composition is in progress
+//
+// # Safety
+// `engine` must be a live engine pointer; `out_composing` must be valid for a write.
+enum HipKeyResult hipkey_is_composing(struct HipKeyEngine *engine, bool *out_composing);
+
+// Report the number of current candidates
+//
+// # Safety
+// `engine` must be a live engine pointer; `out_count` must be valid for a write.
+enum HipKeyResult hipkey_get_candidate_count(struct HipKeyEngine *engine, size_t *out_count);
+
+// Copy the text of the candidate at `index`
+//
+// # Safety
+// Same as `hipkey_get_composing_text`.
+enum HipKeyResult hipkey_get_candidate(struct HipKeyEngine *engine,
+                                       size_t index,
+                                       char *buf,
+                                       size_t capacity,
+                                       size_t *out_len);
+
+// Report the id, source, kind and confidence of the candidate at `index`
+//
+// # Safety
+// `engine` must be a live engine pointer; `out_info` must be valid for a write.
+enum HipKeyResult hipkey_get_candidate_info(struct HipKeyEngine *engine,
+                                            size_t index,
+                                            struct HipKeyCandidateInfo *out_info);
+
+// Tell the language pack whether the user accepted or rejected a candidate
+//
+// `candidate_id` is a `HipKeyCandidateInfo::id` from the current candidates
+// or the list shown before them, so feedback may follow a selection.
+// Returns `InvalidArgument` for an unknown id.
+//
+// # Safety
+// `engine` must be a live engine pointer.
+enum HipKeyResult hipkey_candidate_feedback(struct HipKeyEngine *engine,
+                                            uint64_t candidate_id,
+                                            bool accepted);
+
+// Create an empty registry with no language pack loaded
+//
+// Returns null on failure. Release with `hipkey_registry_destroy`.
+struct HipKeyRegistry *hipkey_registry_create(void);
+
+// Destroy a registry and all of its sessions
+//
+// No other thread may be using the registry.
+//
+// # Safety
+// `registry` must be null or a pointer returned by `hipkey_registry_create`
+// that has not been destroyed yet.
+void hipkey_registry_destroy(struct HipKeyRegistry *registry);
+
+// Load the language pack shared by all sessions of the registry
+//
+// Existing sessions switch to the new pack; new sessions start with it.
+//
+// # Safety
+// `registry` must be a live registry pointer; `id` and `method` must be null
+// or NUL-terminated strings.
+enum HipKeyResult hipkey_registry_set_language_pack(struct HipKeyRegistry *registry,
+                                                    const char *id,
+                                                    const char *method);
+
+// Create a session using the registry's language pack
+//
+// # Safety
+// `registry` must be a live registry pointer; `out_id` must be valid for a write.
+enum HipKeyResult hipkey_session_create(struct HipKeyRegistry *registry, HipKeySessionId *out_id);
+
+// Destroy a session
+//
+// Calls already running on the session finish normally; later calls with
+// this id return `InvalidArgument`. Allowed from inside the session's own
+// callbacks.
+//
+// # Safety
+// `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_destroy(struct HipKeyRegistry *registry, HipKeySessionId id);
+
+// Session counterpart of `hipkey_engine_set_callbacks`
+//
+// # Safety
+// See `hipkey_engine_set_callbacks`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_set_callbacks(struct HipKeyRegistry *registry,
+                                               HipKeySessionId id,
+                                               const struct HipKeyCallbacks *callbacks);
+
+// Session counterpart of `hipkey_process_keystroke`
+//
+// # Safety
+// See `hipkey_process_keystroke`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_process_keystroke(struct HipKeyRegistry *registry,
+                                                   HipKeySessionId id,
+                                                   const struct HipKeyKeystroke *keystroke,
+                                                   uint32_t *out_events);
+
+// Session counterpart of `hipkey_get_composing_text`
+//
+// # Safety
+// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_composing_text(struct HipKeyRegistry *registry,
+                                                    HipKeySessionId id,
+                                                    char *buf,
+                                                    size_t capacity,
+                                                    size_t *out_len);
+
+// Session counterpart of `hipkey_get_committed_text`
+//
+// # Safety
+// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_committed_text(struct HipKeyRegistry *registry,
+                                                    HipKeySessionId id,
+                                                    char *buf,
+                                                    size_t capacity,
+                                                    size_t *out_len);
+
+// Session counterpart of `hipkey_get_last_commit_text`
+//
+// # Safety
+// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_last_commit_text(struct HipKeyRegistry *registry,
+                                                      HipKeySessionId id,
+                                                      char *buf,
+                                                      size_t capacity,
+                                                      size_t *out_len);
+
+// Session counterpart of `hipkey_commit`
+//
+// # Safety
+// `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_commit(struct HipKeyRegistry *registry, HipKeySessionId id);
+
+// Session counterpart of `hipkey_clear`
+//
+// # Safety
+// `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_clear(struct HipKeyRegistry *registry, HipKeySessionId id);
+
+// Session counterpart of `hipkey_is_composing`
+//
+// # Safety
+// See `hipkey_is_composing`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_is_composing(struct HipKeyRegistry *registry,
+                                              HipKeySessionId id,
+                                              bool *out_composing);
+
+// Session counterpart of `hipkey_get_candidate_count`
+//
+// # Safety
+// See `hipkey_get_candidate_count`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_candidate_count(struct HipKeyRegistry *registry,
+                                                     HipKeySessionId id,
+                                                     size_t *out_count);
+
+// Session counterpart of `hipkey_get_candidate`
+//
+// # Safety
+// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_candidate(struct HipKeyRegistry *registry,
+                                               HipKeySessionId id,
+                                               size_t index,
+                                               char *buf,
+                                               size_t capacity,
+                                               size_t *out_len);
+
+// Session counterpart of `hipkey_get_candidate_info`
+//
+// # Safety
+// See `hipkey_get_candidate_info`; `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_get_candidate_info(struct HipKeyRegistry *registry,
+                                                    HipKeySessionId id,
+                                                    size_t index,
+                                                    struct HipKeyCandidateInfo *out_info);
+
+// Session counterpart of `hipkey_candidate_feedback`
+//
+// # Safety
+// `registry` must be a live registry pointer.
+enum HipKeyResult hipkey_session_candidate_feedback(struct HipKeyRegistry *registry,
+                                                    HipKeySessionId id,
+                                                    uint64_t candidate_id,
+                                                    bool accepted);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HIPKEY_H */
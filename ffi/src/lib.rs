@@ -1,10 +1,48 @@
 //! C-compatible FFI for hip-key core
 //!
 //! Provides a stable C API for platform adapters.
+//!
+//! Conventions:
+//! - Every function that can fail returns a `HipKeyResult`.
+//! - No call ever unwinds across the boundary; panics become `HipKeyResult::Error`.
+//! - Strings are UTF-8. Text getters copy into a caller-owned buffer and
+//!   always report the required length (excluding the trailing NUL).
+//!
+//! Engines are safe to use from any thread. For hosts with many input
+//! contexts, `HipKeyRegistry` manages sessions that share one language pack
+//! (see the `hipkey_registry_*` and `hipkey_session_*` functions).
+//!
+//! Callbacks registered with `hipkey_engine_set_callbacks` run synchronously
+//! on the thread that made the triggering call, after the engine state has
+//! been updated. From inside a callback, the read-only calls (`hipkey_get_*`,
+//! `hipkey_is_composing`, `hipkey_candidate_feedback`) may be used on the
+//! same engine; any call that
+//! modifies it returns `HipKeyResult::Busy`, and `hipkey_engine_destroy` is
+//! ignored. Calls that modify the engine from other threads meanwhile wait
+//! for the callbacks to return.
+
+use std::ffi::{c_char, c_void, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use hip_key_core::keystroke::ArrowDirection;
+use hip_key_core::{Candidate, CandidateKind, CandidateSource, Key, Keystroke, LanguagePack, Modifiers};
+use hip_key_lang_vi::{InputMethod, Vietnamese};
+
+mod registry;
+mod session;
 
+pub use registry::{HipKeyRegistry, HipKeySessionId};
+use session::Session;
+
+/// ABI major version: bumped on any incompatible change to the C API
+pub const HIPKEY_ABI_VERSION_MAJOR: u32 = 1;
+/// ABI minor version: bumped when functions or constants are added
+pub const HIPKEY_ABI_VERSION_MINOR: u32 = 4;
 
 /// Opaque handle to Engine instance
-#[repr(C)]
+///
+/// Deliberately not `repr(C)`: C only ever sees a forward declaration.
 pub struct HipKeyEngine {
     _private: [u8; 0],
 }
@@ -17,32 +55,875 @@ pub enum HipKeyResult {
     Error = -1,
     InvalidArgument = -2,
     NotReady = -3,
+    /// Output buffer too small; the required length was still reported
+    BufferTooSmall = -4,
+    /// Called from inside one of the engine's callbacks, which cannot modify it
+    Busy = -5,
 }
 
-// TODO: Implement full C API
-// - Engine creation/destruction
-// - Keystroke processing
-// - Buffer access
-// - Candidate retrieval
+/// Key kinds accepted in `HipKeyKeystroke::key`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HipKeyKey {
+    Char = 0,
+    Backspace = 1,
+    Delete = 2,
+    Enter = 3,
+    Escape = 4,
+    Tab = 5,
+    Space = 6,
+    ArrowUp = 7,
+    ArrowDown = 8,
+    ArrowLeft = 9,
+    ArrowRight = 10,
+    Unknown = 11,
+}
 
+/// Values of `HipKeyCandidateInfo::source`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HipKeyCandidateSource {
+    Dictionary = 0,
+    UserDictionary = 1,
+    Macro = 2,
+    History = 3,
+}
+
+/// Values of `HipKeyCandidateInfo::kind`
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HipKeyCandidateKind {
+    Word = 0,
+    Phrase = 1,
+    Emoji = 2,
+    Correction = 3,
+}
+
+/// Modifier bits for `HipKeyKeystroke::modifiers`
+pub const HIPKEY_MOD_SHIFT: u32 = 1 << 0;
+pub const HIPKEY_MOD_CTRL: u32 = 1 << 1;
+pub const HIPKEY_MOD_ALT: u32 = 1 << 2;
+pub const HIPKEY_MOD_META: u32 = 1 << 3;
+/// Caps Lock is on; lowercase letters are then typed uppercase
+pub const HIPKEY_MOD_CAPS_LOCK: u32 = 1 << 4;
+
+/// Event bits reported by `hipkey_process_keystroke`
+///
+/// When both are set, the commit happens before the pass-through. A commit
+/// always empties the preedit, so it comes with `HIPKEY_EVENT_BUFFER_CHANGED`.
+pub const HIPKEY_EVENT_BUFFER_CHANGED: u32 = 1 << 0;
+pub const HIPKEY_EVENT_CANDIDATES_UPDATED: u32 = 1 << 1;
+pub const HIPKEY_EVENT_COMMIT: u32 = 1 << 2;
+pub const HIPKEY_EVENT_PASS_THROUGH: u32 = 1 << 3;
+
+/// A keystroke as seen by the C API
+///
+/// `key` is a `HipKeyKey` value. `code` is the Unicode scalar value for
+/// `Char`, the platform key code for `Unknown`, and ignored otherwise.
+/// `modifiers` is a combination of `HIPKEY_MOD_*` bits.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HipKeyKeystroke {
+    pub key: u32,
+    pub code: u32,
+    pub modifiers: u32,
+}
+
+/// Event callbacks for an engine
+///
+/// Any callback may be null. Text arguments are NUL-terminated UTF-8 of
+/// `len` bytes. Pointers passed to a callback are only valid for the
+/// duration of that call.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HipKeyCallbacks {
+    /// Passed back unchanged as the first argument of every callback
+    pub user_data: *mut c_void,
+    /// Composing text changed
+    pub on_preedit_changed: Option<unsafe extern "C" fn(user_data: *mut c_void, text: *const c_char, len: usize)>,
+    /// Candidate list changed; `count` is the new number of candidates
+    pub on_candidates_updated: Option<unsafe extern "C" fn(user_data: *mut c_void, count: usize)>,
+    /// Text to insert into the document
+    pub on_commit: Option<unsafe extern "C" fn(user_data: *mut c_void, text: *const c_char, len: usize)>,
+    /// Keystroke not consumed by the engine, for the host to handle itself
+    pub on_pass_through: Option<unsafe extern "C" fn(user_data: *mut c_void, keystroke: *const HipKeyKeystroke)>,
+}
+
+/// Metadata of a candidate, as seen by the C API
+///
+/// `id` stays the same for the same candidate across calls and runs; pass
+/// it to `hipkey_candidate_feedback`. `source` is a `HipKeyCandidateSource`
+/// value and `kind` a `HipKeyCandidateKind` value.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HipKeyCandidateInfo {
+    pub id: u64,
+    pub source: u32,
+    pub kind: u32,
+    pub confidence: f32,
+}
+
+impl From<&Candidate> for HipKeyCandidateInfo {
+    fn from(candidate: &Candidate) -> Self {
+        let source = match candidate.source {
+            CandidateSource::Dictionary => HipKeyCandidateSource::Dictionary,
+            CandidateSource::UserDictionary => HipKeyCandidateSource::UserDictionary,
+            CandidateSource::Macro => HipKeyCandidateSource::Macro,
+            CandidateSource::History => HipKeyCandidateSource::History,
+        };
+        let kind = match candidate.kind {
+            CandidateKind::Word => HipKeyCandidateKind::Word,
+            CandidateKind::Phrase => HipKeyCandidateKind::Phrase,
+            CandidateKind::Emoji => HipKeyCandidateKind::Emoji,
+            CandidateKind::Correction => HipKeyCandidateKind::Correction,
+        };
+        Self { id: candidate.id, source: source as u32, kind: kind as u32, confidence: candidate.confidence }
+    }
+}
+
+/// Run `f`, turning a panic into `HipKeyResult::Error`
+fn guard<F: FnOnce() -> HipKeyResult>(f: F) -> HipKeyResult {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(HipKeyResult::Error)
+}
+
+/// Run `f` on the session behind an engine pointer
+///
+/// # Safety
+/// `engine` must be null or a live pointer from `hipkey_engine_create`.
+unsafe fn with_engine<F: FnOnce(&Session) -> HipKeyResult>(engine: *mut HipKeyEngine, f: F) -> HipKeyResult {
+    match (engine as *const Session).as_ref() {
+        Some(session) => guard(|| f(session)),
+        None => HipKeyResult::InvalidArgument,
+    }
+}
+
+/// Load a language pack from C string arguments
+///
+/// # Safety
+/// `id` and `method` must be null or NUL-terminated strings.
+unsafe fn load_language_pack_c(id: *const c_char, method: *const c_char) -> Result<Arc<dyn LanguagePack>, HipKeyResult> {
+    match (opt_str(id)?, opt_str(method)?) {
+        (Some(id), method) => load_language_pack(id, method).ok_or(HipKeyResult::InvalidArgument),
+        (None, _) => Err(HipKeyResult::InvalidArgument),
+    }
+}
+
+/// Read an optional C string argument
+///
+/// # Safety
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn opt_str<'a>(s: *const c_char) -> Result<Option<&'a str>, HipKeyResult> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map(Some)
+        .map_err(|_| HipKeyResult::InvalidArgument)
+}
+
+/// Copy `text` into a caller buffer as a NUL-terminated string
+///
+/// A null `buf` is a size query: only `out_len` is written.
+///
+/// # Safety
+/// `buf` must be null or valid for `capacity` bytes; `out_len` must be null
+/// or valid for a write.
+pub(crate) unsafe fn write_str(text: &str, buf: *mut c_char, capacity: usize, out_len: *mut usize) -> HipKeyResult {
+    if !out_len.is_null() {
+        *out_len = text.len();
+    }
+    if buf.is_null() {
+        return HipKeyResult::Success;
+    }
+    if capacity <= text.len() {
+        return HipKeyResult::BufferTooSmall;
+    }
+    std::ptr::copy_nonoverlapping(text.as_ptr(), buf as *mut u8, text.len());
+    *buf.add(text.len()) = 0;
+    HipKeyResult::Success
+}
+
+/// Build a language pack from its id and optional method name
+fn load_language_pack(id: &str, method: Option<&str>) -> Option<Arc<dyn LanguagePack>> {
+    match id {
+        "vi" => {
+            let method = match method.map(str::to_ascii_lowercase).as_deref() {
+                None | Some("") => InputMethod::default(),
+                Some(id) => InputMethod::from_id(id)?,
+            };
+            Some(Arc::new(Vietnamese::with_method(method)))
+        }
+        _ => None,
+    }
+}
+
+/// Convert a C keystroke into a core keystroke
+pub(crate) fn to_keystroke(ks: &HipKeyKeystroke) -> Option<Keystroke> {
+    let key = match ks.key {
+        0 => Key::Char(char::from_u32(ks.code)?),
+        1 => Key::Backspace,
+        2 => Key::Delete,
+        3 => Key::Enter,
+        4 => Key::Escape,
+        5 => Key::Tab,
+        6 => Key::Space,
+        7 => Key::Arrow(ArrowDirection::Up),
+        8 => Key::Arrow(ArrowDirection::Down),
+        9 => Key::Arrow(ArrowDirection::Left),
+        10 => Key::Arrow(ArrowDirection::Right),
+        11 => Key::Unknown(ks.code),
+        _ => return None,
+    };
+    let modifiers = Modifiers {
+        shift: ks.modifiers & HIPKEY_MOD_SHIFT != 0,
+        ctrl: ks.modifiers & HIPKEY_MOD_CTRL != 0,
+        alt: ks.modifiers & HIPKEY_MOD_ALT != 0,
+        meta: ks.modifiers & HIPKEY_MOD_META != 0,
+        caps_lock: ks.modifiers & HIPKEY_MOD_CAPS_LOCK != 0,
+    };
+    Some(Keystroke { key, modifiers })
+}
+
+/// ABI version of the loaded library, encoded as `(major << 16) | minor`
+///
+/// Adapters should refuse to run if the major version differs from the
+/// `HIPKEY_ABI_VERSION_MAJOR` they were compiled against.
+#[no_mangle]
+pub extern "C" fn hipkey_abi_version() -> u32 {
+    (HIPKEY_ABI_VERSION_MAJOR << 16) | HIPKEY_ABI_VERSION_MINOR
+}
+
+/// Create a new engine with no language pack loaded
+///
+/// Returns null on failure. Release with `hipkey_engine_destroy`.
 #[no_mangle]
 pub extern "C" fn hipkey_engine_create() -> *mut HipKeyEngine {
-    // Placeholder: return null until implemented
-    std::ptr::null_mut()
+    panic::catch_unwind(|| Box::into_raw(Box::new(Session::new())) as *mut HipKeyEngine)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Destroy an engine created by `hipkey_engine_create`
+///
+/// Ignored when called from inside one of the engine's callbacks. No other
+/// thread may be using the engine.
+///
+/// # Safety
+/// `engine` must be null or a pointer returned by `hipkey_engine_create`
+/// that has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_engine_destroy(engine: *mut HipKeyEngine) {
+    let session = engine as *mut Session;
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| match session.as_ref() {
+        Some(s) if !s.is_dispatching() => drop(Box::from_raw(session)),
+        _ => {}
+    }));
+}
+
+/// Register event callbacks, replacing any previous set
+///
+/// Pass null to remove callbacks. The struct is copied; `user_data` must stay
+/// valid until the callbacks are replaced or the engine is destroyed.
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `callbacks` must be null or point
+/// to a valid `HipKeyCallbacks` whose function pointers are safe to call.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_engine_set_callbacks(
+    engine: *mut HipKeyEngine,
+    callbacks: *const HipKeyCallbacks,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.set_callbacks(callbacks.as_ref().copied()))
+}
+
+/// Load a language pack by id (e.g. `"vi"`) and method (e.g. `"telex"`,
+/// `"simple-telex"`, `"telex2"`, `"vni"`, `"viqr"`, `"accentless"`)
+///
+/// `method` may be null to use the pack's default.
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `id` and `method` must be null or
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_engine_set_language_pack(
+    engine: *mut HipKeyEngine,
+    id: *const c_char,
+    method: *const c_char,
+) -> HipKeyResult {
+    with_engine(engine, |session| match load_language_pack_c(id, method) {
+        Ok(pack) => session.set_language_pack(pack),
+        Err(code) => code,
+    })
+}
+
+/// Process a keystroke
+///
+/// `out_events`, if not null, receives a combination of `HIPKEY_EVENT_*` bits.
+/// After `HIPKEY_EVENT_COMMIT`, read the text with `hipkey_get_last_commit_text`.
+/// When both `HIPKEY_EVENT_COMMIT` and `HIPKEY_EVENT_PASS_THROUGH` are set
+/// (Space, Tab, Enter, arrows), insert the commit before forwarding the key.
+/// Registered callbacks fire before this returns; `on_commit` is always
+/// followed by `on_preedit_changed` with empty text, as with `hipkey_commit`.
+/// Returns `NotReady` if no language pack is loaded.
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `keystroke` must point to a valid
+/// `HipKeyKeystroke`; `out_events` must be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_process_keystroke(
+    engine: *mut HipKeyEngine,
+    keystroke: *const HipKeyKeystroke,
+    out_events: *mut u32,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.process_keystroke(keystroke, out_events))
+}
+
+/// Copy the current composing (preedit) text
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `buf` must be null or valid for
+/// `capacity` bytes; `out_len` must be null or valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_composing_text(
+    engine: *mut HipKeyEngine,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.composing_text(buf, capacity, out_len))
+}
+
+/// Copy all text committed so far in this engine
+///
+/// # Safety
+/// Same as `hipkey_get_composing_text`.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_committed_text(
+    engine: *mut HipKeyEngine,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.committed_text(buf, capacity, out_len))
+}
+
+/// Copy the text of the most recent commit
+///
+/// # Safety
+/// Same as `hipkey_get_composing_text`.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_last_commit_text(
+    engine: *mut HipKeyEngine,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.last_commit_text(buf, capacity, out_len))
+}
+
+/// Commit the current composition
+///
+/// The committed text is available through `hipkey_get_last_commit_text`
+/// and is also delivered to `on_commit`, followed by the emptied preedit.
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_commit(engine: *mut HipKeyEngine) -> HipKeyResult {
+    with_engine(engine, Session::commit)
+}
+
+/// Drop the current composition and candidates without committing
+///
+/// Delivers the emptied preedit to `on_preedit_changed`.
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_clear(engine: *mut HipKeyEngine) -> HipKeyResult {
+    with_engine(engine, Session::clear)
+}
+
+/// Report whether a composition is in progress
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `out_composing` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_is_composing(
+    engine: *mut HipKeyEngine,
+    out_composing: *mut bool,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.is_composing(out_composing))
+}
+
+/// Report the number of current candidates
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `out_count` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_candidate_count(
+    engine: *mut HipKeyEngine,
+    out_count: *mut usize,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.candidate_count(out_count))
+}
+
+/// Copy the text of the candidate at `index`
+///
+/// # Safety
+/// Same as `hipkey_get_composing_text`.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_candidate(
+    engine: *mut HipKeyEngine,
+    index: usize,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.candidate(index, buf, capacity, out_len))
+}
+
+/// Report the id, source, kind and confidence of the candidate at `index`
+///
+/// # Safety
+/// `engine` must be a live engine pointer; `out_info` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_get_candidate_info(
+    engine: *mut HipKeyEngine,
+    index: usize,
+    out_info: *mut HipKeyCandidateInfo,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.candidate_info(index, out_info))
 }
 
+/// Tell the language pack whether the user accepted or rejected a candidate
+///
+/// `candidate_id` is a `HipKeyCandidateInfo::id` from the current candidates
+/// or the list shown before them, so feedback may follow a selection.
+/// Returns `InvalidArgument` for an unknown id.
+///
+/// # Safety
+/// `engine` must be a live engine pointer.
 #[no_mangle]
-pub extern "C" fn hipkey_engine_destroy(_engine: *mut HipKeyEngine) {
-    // Placeholder
+pub unsafe extern "C" fn hipkey_candidate_feedback(
+    engine: *mut HipKeyEngine,
+    candidate_id: u64,
+    accepted: bool,
+) -> HipKeyResult {
+    with_engine(engine, |session| session.candidate_feedback(candidate_id, accepted))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::ffi::CString;
+
+    fn char_key(c: char) -> HipKeyKeystroke {
+        HipKeyKeystroke { key: HipKeyKey::Char as u32, code: c as u32, modifiers: 0 }
+    }
+
+    unsafe fn composing(engine: *mut HipKeyEngine) -> String {
+        let mut buf = [0 as c_char; 64];
+        let mut len = 0;
+        assert_eq!(
+            hipkey_get_composing_text(engine, buf.as_mut_ptr(), buf.len(), &mut len),
+            HipKeyResult::Success
+        );
+        CStr::from_ptr(buf.as_ptr()).to_str().unwrap().to_string()
+    }
+
+    unsafe fn telex_engine() -> *mut HipKeyEngine {
+        let engine = hipkey_engine_create();
+        assert!(!engine.is_null());
+        let id = CString::new("vi").unwrap();
+        let method = CString::new("telex").unwrap();
+        assert_eq!(
+            hipkey_engine_set_language_pack(engine, id.as_ptr(), method.as_ptr()),
+            HipKeyResult::Success
+        );
+        engine
+    }
 
     #[test]
     fn test_result_codes() {
         assert_eq!(HipKeyResult::Success as i32, 0);
         assert_eq!(HipKeyResult::Error as i32, -1);
     }
+
+    #[test]
+    fn test_abi_version() {
+        let version = hipkey_abi_version();
+        assert_eq!(version >> 16, HIPKEY_ABI_VERSION_MAJOR);
+        assert_eq!(version & 0xffff, HIPKEY_ABI_VERSION_MINOR);
+    }
+
+    #[test]
+    fn test_create_destroy() {
+        let engine = hipkey_engine_create();
+        assert!(!engine.is_null());
+        unsafe {
+            hipkey_engine_destroy(engine);
+            hipkey_engine_destroy(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_process_without_language_pack() {
+        unsafe {
+            let engine = hipkey_engine_create();
+            let ks = char_key('a');
+            assert_eq!(hipkey_process_keystroke(engine, &ks, std::ptr::null_mut()), HipKeyResult::NotReady);
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_set_language_pack_invalid() {
+        unsafe {
+            let engine = hipkey_engine_create();
+            let unknown = CString::new("xx").unwrap();
+            assert_eq!(
+                hipkey_engine_set_language_pack(engine, unknown.as_ptr(), std::ptr::null()),
+                HipKeyResult::InvalidArgument
+            );
+            let vi = CString::new("vi").unwrap();
+            let bad_method = CString::new("morse").unwrap();
+            assert_eq!(
+                hipkey_engine_set_language_pack(engine, vi.as_ptr(), bad_method.as_ptr()),
+                HipKeyResult::InvalidArgument
+            );
+            assert_eq!(
+                hipkey_engine_set_language_pack(engine, std::ptr::null(), std::ptr::null()),
+                HipKeyResult::InvalidArgument
+            );
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_set_language_pack_methods() {
+        unsafe {
+            let engine = hipkey_engine_create();
+            let vi = CString::new("vi").unwrap();
+            for name in ["telex", "simple-telex", "Telex2", "VNI", "viqr", "accentless"] {
+                let method = CString::new(name).unwrap();
+                assert_eq!(
+                    hipkey_engine_set_language_pack(engine, vi.as_ptr(), method.as_ptr()),
+                    HipKeyResult::Success,
+                    "{}",
+                    name
+                );
+            }
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_telex_session() {
+        unsafe {
+            let engine = telex_engine();
+            let mut events = 0;
+            for c in "vieet".chars() {
+                assert_eq!(hipkey_process_keystroke(engine, &char_key(c), &mut events), HipKeyResult::Success);
+                assert_eq!(events, HIPKEY_EVENT_BUFFER_CHANGED);
+            }
+            assert_eq!(composing(engine), "viêt");
+
+            let mut is_composing = false;
+            assert_eq!(hipkey_is_composing(engine, &mut is_composing), HipKeyResult::Success);
+            assert!(is_composing);
+
+            assert_eq!(hipkey_commit(engine), HipKeyResult::Success);
+            let mut buf = [0 as c_char; 16];
+            let mut len = 0;
+            assert_eq!(
+                hipkey_get_last_commit_text(engine, buf.as_mut_ptr(), buf.len(), &mut len),
+                HipKeyResult::Success
+            );
+            assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str().unwrap(), "viêt");
+            assert_eq!(len, "viêt".len());
+
+            assert_eq!(hipkey_is_composing(engine, &mut is_composing), HipKeyResult::Success);
+            assert!(!is_composing);
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_caps_lock_and_shift() {
+        unsafe {
+            let engine = telex_engine();
+            for (c, modifiers) in [
+                ('v', HIPKEY_MOD_SHIFT),
+                ('i', HIPKEY_MOD_CAPS_LOCK),
+                ('e', HIPKEY_MOD_CAPS_LOCK),
+                ('e', 0),
+                ('t', HIPKEY_MOD_CAPS_LOCK | HIPKEY_MOD_SHIFT),
+            ] {
+                let ks = HipKeyKeystroke { modifiers, ..char_key(c) };
+                assert_eq!(hipkey_process_keystroke(engine, &ks, std::ptr::null_mut()), HipKeyResult::Success);
+            }
+            assert_eq!(composing(engine), "VIÊt");
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_text_buffer_too_small() {
+        unsafe {
+            let engine = telex_engine();
+            for c in "aa".chars() {
+                hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut());
+            }
+
+            // Size query
+            let mut len = 0;
+            assert_eq!(
+                hipkey_get_composing_text(engine, std::ptr::null_mut(), 0, &mut len),
+                HipKeyResult::Success
+            );
+            assert_eq!(len, "â".len());
+
+            // No room for the NUL terminator
+            let mut buf = [0 as c_char; 2];
+            assert_eq!(
+                hipkey_get_composing_text(engine, buf.as_mut_ptr(), buf.len(), &mut len),
+                HipKeyResult::BufferTooSmall
+            );
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_clear_and_candidates() {
+        unsafe {
+            let engine = telex_engine();
+            hipkey_process_keystroke(engine, &char_key('a'), std::ptr::null_mut());
+            assert_eq!(hipkey_clear(engine), HipKeyResult::Success);
+            assert_eq!(composing(engine), "");
+
+            let mut count = 1;
+            assert_eq!(hipkey_get_candidate_count(engine, &mut count), HipKeyResult::Success);
+            assert_eq!(count, 0);
+            assert_eq!(
+                hipkey_get_candidate(engine, 0, std::ptr::null_mut(), 0, std::ptr::null_mut()),
+                HipKeyResult::InvalidArgument
+            );
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_accentless_candidates() {
+        unsafe {
+            let engine = hipkey_engine_create();
+            let id = CString::new("vi").unwrap();
+            let method = CString::new("accentless").unwrap();
+            assert_eq!(
+                hipkey_engine_set_language_pack(engine, id.as_ptr(), method.as_ptr()),
+                HipKeyResult::Success
+            );
+            for c in "viet".chars() {
+                assert_eq!(hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut()), HipKeyResult::Success);
+            }
+
+            let mut count = 0;
+            assert_eq!(hipkey_get_candidate_count(engine, &mut count), HipKeyResult::Success);
+            assert!(count > 0);
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_candidate_info_and_feedback() {
+        unsafe {
+            let engine = hipkey_engine_create();
+            let id = CString::new("vi").unwrap();
+            let method = CString::new("accentless").unwrap();
+            assert_eq!(
+                hipkey_engine_set_language_pack(engine, id.as_ptr(), method.as_ptr()),
+                HipKeyResult::Success
+            );
+            for c in "viet".chars() {
+                hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut());
+            }
+
+            let mut info = HipKeyCandidateInfo { id: 0, source: 9, kind: 9, confidence: 0.0 };
+            assert_eq!(hipkey_get_candidate_info(engine, 1, &mut info), HipKeyResult::Success);
+            assert_eq!(info.id, Candidate::stable_id("Việt Nam", None));
+            assert_eq!(info.source, HipKeyCandidateSource::Dictionary as u32);
+            assert_eq!(info.kind, HipKeyCandidateKind::Phrase as u32);
+            assert!(info.confidence > 0.0);
+            assert_eq!(hipkey_get_candidate_info(engine, 999, &mut info), HipKeyResult::InvalidArgument);
+            assert_eq!(hipkey_get_candidate_info(engine, 0, std::ptr::null_mut()), HipKeyResult::InvalidArgument);
+
+            // The id comes back after the selection cleared the list
+            hipkey_process_keystroke(engine, &char_key('2'), std::ptr::null_mut());
+            assert_eq!(hipkey_candidate_feedback(engine, info.id, true), HipKeyResult::Success);
+            assert_eq!(hipkey_candidate_feedback(engine, 42, false), HipKeyResult::InvalidArgument);
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    /// Events recorded by the test callbacks
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        /// Engine to poke at from inside callbacks
+        engine: Option<*mut HipKeyEngine>,
+        reentrant_results: Vec<HipKeyResult>,
+    }
+
+    unsafe extern "C" fn record_preedit(user_data: *mut c_void, text: *const c_char, len: usize) {
+        let recorder = &mut *(user_data as *mut Recorder);
+        let text = CStr::from_ptr(text).to_str().unwrap();
+        assert_eq!(text.len(), len);
+        recorder.events.push(format!("preedit:{}", text));
+
+        if let Some(engine) = recorder.engine {
+            // Read-only calls are allowed, modifying calls are refused
+            assert_eq!(composing(engine), text);
+            recorder.reentrant_results.push(hipkey_clear(engine));
+            recorder.reentrant_results.push(hipkey_process_keystroke(engine, &char_key('x'), std::ptr::null_mut()));
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    unsafe extern "C" fn record_commit(user_data: *mut c_void, text: *const c_char, _len: usize) {
+        let recorder = &mut *(user_data as *mut Recorder);
+        recorder.events.push(format!("commit:{}", CStr::from_ptr(text).to_str().unwrap()));
+    }
+
+    unsafe extern "C" fn record_candidates(user_data: *mut c_void, count: usize) {
+        let recorder = &mut *(user_data as *mut Recorder);
+        recorder.events.push(format!("candidates:{}", count));
+    }
+
+    unsafe extern "C" fn record_pass_through(user_data: *mut c_void, keystroke: *const HipKeyKeystroke) {
+        let recorder = &mut *(user_data as *mut Recorder);
+        recorder.events.push(format!("pass:{}", (*keystroke).key));
+    }
+
+    fn callbacks_for(recorder: &mut Recorder) -> HipKeyCallbacks {
+        HipKeyCallbacks {
+            user_data: recorder as *mut Recorder as *mut c_void,
+            on_preedit_changed: Some(record_preedit),
+            on_candidates_updated: Some(record_candidates),
+            on_commit: Some(record_commit),
+            on_pass_through: Some(record_pass_through),
+        }
+    }
+
+    #[test]
+    fn test_callbacks_fire_per_event() {
+        unsafe {
+            let engine = telex_engine();
+            let mut recorder = Recorder::default();
+            let callbacks = callbacks_for(&mut recorder);
+            assert_eq!(hipkey_engine_set_callbacks(engine, &callbacks), HipKeyResult::Success);
+
+            for c in "aa".chars() {
+                hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut());
+            }
+            let unknown = HipKeyKeystroke { key: HipKeyKey::Unknown as u32, code: 42, modifiers: 0 };
+            hipkey_process_keystroke(engine, &unknown, std::ptr::null_mut());
+            hipkey_commit(engine);
+            hipkey_clear(engine);
+
+            assert_eq!(
+                recorder.events,
+                ["preedit:a", "preedit:â", "pass:11", "commit:â", "preedit:", "preedit:"]
+            );
+
+            // Removing callbacks stops delivery
+            assert_eq!(hipkey_engine_set_callbacks(engine, std::ptr::null()), HipKeyResult::Success);
+            hipkey_process_keystroke(engine, &char_key('a'), std::ptr::null_mut());
+            assert_eq!(recorder.events.len(), 6);
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_commit_keys() {
+        unsafe {
+            let engine = telex_engine();
+            let mut recorder = Recorder::default();
+            let callbacks = callbacks_for(&mut recorder);
+            assert_eq!(hipkey_engine_set_callbacks(engine, &callbacks), HipKeyResult::Success);
+
+            for c in "aa".chars() {
+                hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut());
+            }
+            let space = HipKeyKeystroke { key: HipKeyKey::Space as u32, code: 0, modifiers: 0 };
+            let mut events = 0;
+            assert_eq!(hipkey_process_keystroke(engine, &space, &mut events), HipKeyResult::Success);
+            assert_eq!(events, HIPKEY_EVENT_COMMIT | HIPKEY_EVENT_BUFFER_CHANGED | HIPKEY_EVENT_PASS_THROUGH);
+
+            // Escape gives back the keys as typed
+            for c in "aa".chars() {
+                hipkey_process_keystroke(engine, &char_key(c), std::ptr::null_mut());
+            }
+            let escape = HipKeyKeystroke { key: HipKeyKey::Escape as u32, code: 0, modifiers: 0 };
+            assert_eq!(hipkey_process_keystroke(engine, &escape, &mut events), HipKeyResult::Success);
+            assert_eq!(events, HIPKEY_EVENT_COMMIT | HIPKEY_EVENT_BUFFER_CHANGED);
+            assert_eq!(hipkey_process_keystroke(engine, &escape, &mut events), HipKeyResult::Success);
+            assert_eq!(events, HIPKEY_EVENT_PASS_THROUGH);
+
+            let space_code = HipKeyKey::Space as u32;
+            let escape_code = HipKeyKey::Escape as u32;
+            assert_eq!(
+                recorder.events,
+                [
+                    "preedit:a".to_string(),
+                    "preedit:â".to_string(),
+                    "commit:â".to_string(),
+                    "preedit:".to_string(),
+                    format!("pass:{}", space_code),
+                    "preedit:a".to_string(),
+                    "preedit:â".to_string(),
+                    "commit:aa".to_string(),
+                    "preedit:".to_string(),
+                    format!("pass:{}", escape_code),
+                ]
+            );
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_callbacks_reentrancy() {
+        unsafe {
+            let engine = telex_engine();
+            let mut recorder = Recorder { engine: Some(engine), ..Recorder::default() };
+            let callbacks = callbacks_for(&mut recorder);
+            hipkey_engine_set_callbacks(engine, &callbacks);
+
+            assert_eq!(hipkey_process_keystroke(engine, &char_key('a'), std::ptr::null_mut()), HipKeyResult::Success);
+            assert_eq!(recorder.reentrant_results, [HipKeyResult::Busy, HipKeyResult::Busy]);
+
+            // The engine survived the destroy attempt and is usable again
+            recorder.engine = None;
+            assert_eq!(hipkey_process_keystroke(engine, &char_key('a'), std::ptr::null_mut()), HipKeyResult::Success);
+            assert_eq!(composing(engine), "â");
+            hipkey_engine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_null_arguments() {
+        unsafe {
+            let null = std::ptr::null_mut();
+            assert_eq!(hipkey_commit(null), HipKeyResult::InvalidArgument);
+            assert_eq!(hipkey_clear(null), HipKeyResult::InvalidArgument);
+            assert_eq!(hipkey_is_composing(null, std::ptr::null_mut()), HipKeyResult::InvalidArgument);
+            assert_eq!(hipkey_process_keystroke(null, std::ptr::null(), std::ptr::null_mut()), HipKeyResult::InvalidArgument);
+
+            let engine = telex_engine();
+            let bad = HipKeyKeystroke { key: 99, code: 0, modifiers: 0 };
+            assert_eq!(hipkey_process_keystroke(engine, &bad, std::ptr::null_mut()), HipKeyResult::InvalidArgument);
+            let surrogate = HipKeyKeystroke { key: HipKeyKey::Char as u32, code: 0xD800, modifiers: 0 };
+            assert_eq!(hipkey_process_keystroke(engine, &surrogate, std::ptr::null_mut()), HipKeyResult::InvalidArgument);
+            hipkey_engine_destroy(engine);
+        }
+    }
 }
@@ -0,0 +1,603 @@
+//! Multi-session registry for IME hosts
+//!
+//! Hosts create one session per input context (focused text field). All
+//! sessions of a registry share one loaded language pack through an `Arc`;
+//! each keeps its own composition, commit text and callbacks.
+//!
+//! Sessions are addressed by id rather than pointer, so a session destroyed
+//! on one thread cannot be used after free on another: calls with a stale id
+//! return `HipKeyResult::InvalidArgument`, and a call already in progress
+//! keeps the session alive until it returns.
+
+use std::collections::HashMap;
+use std::ffi::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use hip_key_core::LanguagePack;
+
+use crate::session::Session;
+use crate::{guard, load_language_pack_c, HipKeyCallbacks, HipKeyCandidateInfo, HipKeyKeystroke, HipKeyResult};
+
+/// Opaque handle to a session registry
+pub struct HipKeyRegistry {
+    _private: [u8; 0],
+}
+
+/// Identifier of a session within its registry; never 0
+pub type HipKeySessionId = u64;
+
+/// State behind a `HipKeyRegistry` pointer
+struct Registry {
+    pack: RwLock<Option<Arc<dyn LanguagePack>>>,
+    sessions: RwLock<HashMap<HipKeySessionId, Arc<Session>>>,
+    next_id: AtomicU64,
+}
+
+impl Registry {
+    fn new() -> Self {
+        Self {
+            pack: RwLock::new(None),
+            sessions: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn session(&self, id: HipKeySessionId) -> Option<Arc<Session>> {
+        let sessions = self.sessions.read().unwrap_or_else(PoisonError::into_inner);
+        sessions.get(&id).cloned()
+    }
+
+    fn set_language_pack(&self, pack: Arc<dyn LanguagePack>) {
+        // Holding the pack lock keeps sessions created meanwhile from
+        // starting with the old pack
+        let mut current = self.pack.write().unwrap_or_else(PoisonError::into_inner);
+        *current = Some(Arc::clone(&pack));
+        let sessions: Vec<Arc<Session>> = {
+            let sessions = self.sessions.read().unwrap_or_else(PoisonError::into_inner);
+            sessions.values().cloned().collect()
+        };
+        for session in sessions {
+            session.replace_language_pack(Arc::clone(&pack));
+        }
+    }
+
+    fn create_session(&self) -> HipKeySessionId {
+        let pack = self.pack.read().unwrap_or_else(PoisonError::into_inner);
+        let session = Session::new();
+        if let Some(pack) = pack.as_ref() {
+            session.replace_language_pack(Arc::clone(pack));
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, Arc::new(session));
+        id
+    }
+
+    fn destroy_session(&self, id: HipKeySessionId) -> bool {
+        self.sessions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&id)
+            .is_some()
+    }
+}
+
+/// Run `f` on a registry
+///
+/// # Safety
+/// `registry` must be null or a live pointer from `hipkey_registry_create`.
+unsafe fn with_registry<F: FnOnce(&Registry) -> HipKeyResult>(registry: *mut HipKeyRegistry, f: F) -> HipKeyResult {
+    match (registry as *const Registry).as_ref() {
+        Some(registry) => guard(|| f(registry)),
+        None => HipKeyResult::InvalidArgument,
+    }
+}
+
+/// Run `f` on a session of a registry
+///
+/// # Safety
+/// Same as `with_registry`.
+unsafe fn with_session<F: FnOnce(&Session) -> HipKeyResult>(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    f: F,
+) -> HipKeyResult {
+    with_registry(registry, |registry| match registry.session(id) {
+        Some(session) => f(&session),
+        None => HipKeyResult::InvalidArgument,
+    })
+}
+
+/// Create an empty registry with no language pack loaded
+///
+/// Returns null on failure. Release with `hipkey_registry_destroy`.
+#[no_mangle]
+pub extern "C" fn hipkey_registry_create() -> *mut HipKeyRegistry {
+    panic::catch_unwind(|| Box::into_raw(Box::new(Registry::new())) as *mut HipKeyRegistry)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Destroy a registry and all of its sessions
+///
+/// No other thread may be using the registry.
+///
+/// # Safety
+/// `registry` must be null or a pointer returned by `hipkey_registry_create`
+/// that has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_registry_destroy(registry: *mut HipKeyRegistry) {
+    if registry.is_null() {
+        return;
+    }
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        drop(Box::from_raw(registry as *mut Registry));
+    }));
+}
+
+/// Load the language pack shared by all sessions of the registry
+///
+/// Existing sessions switch to the new pack; new sessions start with it.
+///
+/// # Safety
+/// `registry` must be a live registry pointer; `id` and `method` must be null
+/// or NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_registry_set_language_pack(
+    registry: *mut HipKeyRegistry,
+    id: *const c_char,
+    method: *const c_char,
+) -> HipKeyResult {
+    with_registry(registry, |registry| match load_language_pack_c(id, method) {
+        Ok(pack) => {
+            registry.set_language_pack(pack);
+            HipKeyResult::Success
+        }
+        Err(code) => code,
+    })
+}
+
+/// Create a session using the registry's language pack
+///
+/// # Safety
+/// `registry` must be a live registry pointer; `out_id` must be valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_create(
+    registry: *mut HipKeyRegistry,
+    out_id: *mut HipKeySessionId,
+) -> HipKeyResult {
+    with_registry(registry, |registry| match out_id.as_mut() {
+        Some(out) => {
+            *out = registry.create_session();
+            HipKeyResult::Success
+        }
+        None => HipKeyResult::InvalidArgument,
+    })
+}
+
+/// Destroy a session
+///
+/// Calls already running on the session finish normally; later calls with
+/// this id return `InvalidArgument`. Allowed from inside the session's own
+/// callbacks.
+///
+/// # Safety
+/// `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_destroy(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+) -> HipKeyResult {
+    with_registry(registry, |registry| {
+        if registry.destroy_session(id) {
+            HipKeyResult::Success
+        } else {
+            HipKeyResult::InvalidArgument
+        }
+    })
+}
+
+/// Session counterpart of `hipkey_engine_set_callbacks`
+///
+/// # Safety
+/// See `hipkey_engine_set_callbacks`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_set_callbacks(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    callbacks: *const HipKeyCallbacks,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.set_callbacks(callbacks.as_ref().copied()))
+}
+
+/// Session counterpart of `hipkey_process_keystroke`
+///
+/// # Safety
+/// See `hipkey_process_keystroke`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_process_keystroke(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    keystroke: *const HipKeyKeystroke,
+    out_events: *mut u32,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.process_keystroke(keystroke, out_events))
+}
+
+/// Session counterpart of `hipkey_get_composing_text`
+///
+/// # Safety
+/// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_composing_text(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.composing_text(buf, capacity, out_len))
+}
+
+/// Session counterpart of `hipkey_get_committed_text`
+///
+/// # Safety
+/// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_committed_text(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.committed_text(buf, capacity, out_len))
+}
+
+/// Session counterpart of `hipkey_get_last_commit_text`
+///
+/// # Safety
+/// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_last_commit_text(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.last_commit_text(buf, capacity, out_len))
+}
+
+/// Session counterpart of `hipkey_commit`
+///
+/// # Safety
+/// `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_commit(registry: *mut HipKeyRegistry, id: HipKeySessionId) -> HipKeyResult {
+    with_session(registry, id, Session::commit)
+}
+
+/// Session counterpart of `hipkey_clear`
+///
+/// # Safety
+/// `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_clear(registry: *mut HipKeyRegistry, id: HipKeySessionId) -> HipKeyResult {
+    with_session(registry, id, Session::clear)
+}
+
+/// Session counterpart of `hipkey_is_composing`
+///
+/// # Safety
+/// See `hipkey_is_composing`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_is_composing(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    out_composing: *mut bool,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.is_composing(out_composing))
+}
+
+/// Session counterpart of `hipkey_get_candidate_count`
+///
+/// # Safety
+/// See `hipkey_get_candidate_count`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_candidate_count(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    out_count: *mut usize,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.candidate_count(out_count))
+}
+
+/// Session counterpart of `hipkey_get_candidate`
+///
+/// # Safety
+/// See `hipkey_get_composing_text`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_candidate(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    index: usize,
+    buf: *mut c_char,
+    capacity: usize,
+    out_len: *mut usize,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.candidate(index, buf, capacity, out_len))
+}
+
+/// Session counterpart of `hipkey_get_candidate_info`
+///
+/// # Safety
+/// See `hipkey_get_candidate_info`; `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_get_candidate_info(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    index: usize,
+    out_info: *mut HipKeyCandidateInfo,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.candidate_info(index, out_info))
+}
+
+/// Session counterpart of `hipkey_candidate_feedback`
+///
+/// # Safety
+/// `registry` must be a live registry pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hipkey_session_candidate_feedback(
+    registry: *mut HipKeyRegistry,
+    id: HipKeySessionId,
+    candidate_id: u64,
+    accepted: bool,
+) -> HipKeyResult {
+    with_session(registry, id, |session| session.candidate_feedback(candidate_id, accepted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HipKeyKey;
+    use std::ffi::{c_void, CStr, CString};
+    use std::thread;
+
+    /// Registry pointer that tests may hand to other threads
+    #[derive(Clone, Copy)]
+    struct Shared(*mut HipKeyRegistry);
+    unsafe impl Send for Shared {}
+
+    fn telex_registry() -> *mut HipKeyRegistry {
+        let registry = hipkey_registry_create();
+        assert!(!registry.is_null());
+        let id = CString::new("vi").unwrap();
+        let method = CString::new("telex").unwrap();
+        unsafe {
+            assert_eq!(
+                hipkey_registry_set_language_pack(registry, id.as_ptr(), method.as_ptr()),
+                HipKeyResult::Success
+            );
+        }
+        registry
+    }
+
+    unsafe fn type_text(registry: *mut HipKeyRegistry, id: HipKeySessionId, text: &str) -> HipKeyResult {
+        for c in text.chars() {
+            let ks = HipKeyKeystroke { key: HipKeyKey::Char as u32, code: c as u32, modifiers: 0 };
+            let result = hipkey_session_process_keystroke(registry, id, &ks, std::ptr::null_mut());
+            if result != HipKeyResult::Success {
+                return result;
+            }
+        }
+        HipKeyResult::Success
+    }
+
+    unsafe fn composing(registry: *mut HipKeyRegistry, id: HipKeySessionId) -> String {
+        let mut buf = [0 as c_char; 64];
+        assert_eq!(
+            hipkey_session_get_composing_text(registry, id, buf.as_mut_ptr(), buf.len(), std::ptr::null_mut()),
+            HipKeyResult::Success
+        );
+        CStr::from_ptr(buf.as_ptr()).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_sessions_are_independent() {
+        unsafe {
+            let registry = telex_registry();
+            let (mut first, mut second) = (0, 0);
+            assert_eq!(hipkey_session_create(registry, &mut first), HipKeyResult::Success);
+            assert_eq!(hipkey_session_create(registry, &mut second), HipKeyResult::Success);
+            assert_ne!(first, second);
+
+            type_text(registry, first, "aa");
+            type_text(registry, second, "dd");
+            assert_eq!(composing(registry, first), "â");
+            assert_eq!(composing(registry, second), "đ");
+
+            // All sessions hold the registry's single pack
+            let reg = &*(registry as *const Registry);
+            let pack = reg.pack.read().unwrap().clone().unwrap();
+            assert_eq!(Arc::strong_count(&pack), 4);
+
+            assert_eq!(hipkey_session_destroy(registry, first), HipKeyResult::Success);
+            assert_eq!(hipkey_session_destroy(registry, first), HipKeyResult::InvalidArgument);
+            assert_eq!(type_text(registry, first, "a"), HipKeyResult::InvalidArgument);
+            assert_eq!(composing(registry, second), "đ");
+            hipkey_registry_destroy(registry);
+        }
+    }
+
+    #[test]
+    fn test_session_without_language_pack() {
+        unsafe {
+            let registry = hipkey_registry_create();
+            let mut id = 0;
+            hipkey_session_create(registry, &mut id);
+            assert_eq!(type_text(registry, id, "a"), HipKeyResult::NotReady);
+
+            // Loading a pack later reaches existing sessions
+            let vi = CString::new("vi").unwrap();
+            assert_eq!(
+                hipkey_registry_set_language_pack(registry, vi.as_ptr(), std::ptr::null()),
+                HipKeyResult::Success
+            );
+            assert_eq!(type_text(registry, id, "a"), HipKeyResult::Success);
+            hipkey_registry_destroy(registry);
+        }
+    }
+
+    #[test]
+    fn test_null_registry() {
+        unsafe {
+            let mut id = 0;
+            assert_eq!(hipkey_session_create(std::ptr::null_mut(), &mut id), HipKeyResult::InvalidArgument);
+            assert_eq!(hipkey_session_commit(std::ptr::null_mut(), 1), HipKeyResult::InvalidArgument);
+            hipkey_registry_destroy(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_concurrent_sessions() {
+        let registry = Shared(telex_registry());
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(move || unsafe {
+                    let registry = registry;
+                    for _ in 0..50 {
+                        let mut id = 0;
+                        assert_eq!(hipkey_session_create(registry.0, &mut id), HipKeyResult::Success);
+                        assert_eq!(type_text(registry.0, id, "vieets"), HipKeyResult::Success);
+                        assert_eq!(composing(registry.0, id), "viết");
+                        assert_eq!(hipkey_session_commit(registry.0, id), HipKeyResult::Success);
+                        assert_eq!(hipkey_session_destroy(registry.0, id), HipKeyResult::Success);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        unsafe {
+            assert!((*(registry.0 as *const Registry)).sessions.read().unwrap().is_empty());
+            hipkey_registry_destroy(registry.0);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_use_of_one_session() {
+        let registry = Shared(telex_registry());
+        let mut id = 0;
+        unsafe { hipkey_session_create(registry.0, &mut id) };
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(move || unsafe {
+                    let registry = registry;
+                    for _ in 0..100 {
+                        assert_eq!(type_text(registry.0, id, "b"), HipKeyResult::Success);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        unsafe {
+            // Every keystroke landed exactly once
+            let mut len = 0;
+            hipkey_session_get_composing_text(registry.0, id, std::ptr::null_mut(), 0, &mut len);
+            assert_eq!(len, 400);
+            hipkey_registry_destroy(registry.0);
+        }
+    }
+
+    unsafe extern "C" fn count_preedit(user_data: *mut c_void, _text: *const c_char, _len: usize) {
+        let count = &*(user_data as *const AtomicU64);
+        count.fetch_add(1, Ordering::Relaxed);
+        // Give other threads a chance to call in while callbacks run
+        thread::yield_now();
+    }
+
+    #[test]
+    fn test_concurrent_use_with_callbacks() {
+        let registry = Shared(telex_registry());
+        let mut id = 0;
+        let count = Box::leak(Box::new(AtomicU64::new(0)));
+        let callbacks = HipKeyCallbacks {
+            user_data: count as *mut AtomicU64 as *mut c_void,
+            on_preedit_changed: Some(count_preedit),
+            on_candidates_updated: None,
+            on_commit: None,
+            on_pass_through: None,
+        };
+        unsafe {
+            hipkey_session_create(registry.0, &mut id);
+            assert_eq!(hipkey_session_set_callbacks(registry.0, id, &callbacks), HipKeyResult::Success);
+        }
+
+        // Other threads wait for callbacks instead of getting `Busy`
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(move || unsafe {
+                    let registry = registry;
+                    for _ in 0..100 {
+                        assert_eq!(type_text(registry.0, id, "b"), HipKeyResult::Success);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(count.load(Ordering::Relaxed), 400);
+        unsafe { hipkey_registry_destroy(registry.0) };
+    }
+
+    #[test]
+    fn test_destroy_while_in_use() {
+        let registry = Shared(telex_registry());
+        let ids: Vec<HipKeySessionId> = (0..32)
+            .map(|_| {
+                let mut id = 0;
+                unsafe { hipkey_session_create(registry.0, &mut id) };
+                id
+            })
+            .collect();
+
+        let users: Vec<_> = (0..4)
+            .map(|_| {
+                let ids = ids.clone();
+                thread::spawn(move || unsafe {
+                    let registry = registry;
+                    for _ in 0..20 {
+                        for &id in &ids {
+                            let result = type_text(registry.0, id, "a");
+                            assert!(matches!(result, HipKeyResult::Success | HipKeyResult::InvalidArgument));
+                        }
+                    }
+                })
+            })
+            .collect();
+        let destroyer = {
+            let ids = ids.clone();
+            thread::spawn(move || unsafe {
+                let registry = registry;
+                for &id in &ids {
+                    assert_eq!(hipkey_session_destroy(registry.0, id), HipKeyResult::Success);
+                }
+            })
+        };
+
+        destroyer.join().unwrap();
+        for user in users {
+            user.join().unwrap();
+        }
+        unsafe { hipkey_registry_destroy(registry.0) };
+    }
+}
@@ -0,0 +1,316 @@
+//! One engine session behind the C API
+//!
+//! A session owns an `Engine` plus the FFI-side state around it (last commit,
+//! callbacks). All state sits behind a mutex so a session can be driven from
+//! any thread. The mutex is never held while callbacks run; other threads
+//! wait for them to finish before modifying the session.
+
+use std::ffi::{c_char, CString};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+use hip_key_core::{Engine, EngineEvent, Feedback, LanguagePack};
+
+use crate::{
+    to_keystroke, write_str, HipKeyCallbacks, HipKeyCandidateInfo, HipKeyKeystroke, HipKeyResult,
+    HIPKEY_EVENT_BUFFER_CHANGED, HIPKEY_EVENT_CANDIDATES_UPDATED, HIPKEY_EVENT_COMMIT, HIPKEY_EVENT_PASS_THROUGH,
+};
+
+/// Callbacks stored in a session
+///
+/// The host owns `user_data` and is responsible for it being usable from
+/// whichever thread drives the session.
+#[derive(Clone, Copy)]
+struct Callbacks(HipKeyCallbacks);
+
+// SAFETY: only the raw `user_data` pointer keeps this from being `Send`;
+// the C API documents that the host owns its thread-safety.
+unsafe impl Send for Callbacks {}
+
+struct SessionState {
+    engine: Engine,
+    /// Text of the most recent commit
+    last_commit: String,
+    callbacks: Option<Callbacks>,
+    /// Thread running callbacks, to refuse re-entrant modification from it
+    dispatching: Option<ThreadId>,
+}
+
+/// A callback invocation with its arguments captured up front
+enum Notification {
+    Preedit(CString),
+    Candidates(usize),
+    Commit(CString),
+    PassThrough(HipKeyKeystroke),
+}
+
+impl Notification {
+    fn text(text: &str) -> CString {
+        // Engine text never contains NUL; fall back to empty rather than panic
+        CString::new(text).unwrap_or_default()
+    }
+
+    /// Capture the data a callback needs for `event`
+    fn from_event(event: &EngineEvent, engine: &Engine, keystroke: &HipKeyKeystroke) -> Self {
+        match event {
+            EngineEvent::BufferChanged => Self::Preedit(Self::text(engine.buffer().composing())),
+            EngineEvent::CandidatesUpdated => Self::Candidates(engine.candidates().len()),
+            EngineEvent::Commit(text) => Self::Commit(Self::text(text)),
+            EngineEvent::PassThrough => Self::PassThrough(*keystroke),
+        }
+    }
+
+    /// # Safety
+    /// The callbacks must be valid to call with their `user_data`.
+    unsafe fn deliver(&self, callbacks: &HipKeyCallbacks) {
+        let user_data = callbacks.user_data;
+        match self {
+            Self::Preedit(text) => {
+                if let Some(f) = callbacks.on_preedit_changed {
+                    f(user_data, text.as_ptr(), text.as_bytes().len());
+                }
+            }
+            Self::Candidates(count) => {
+                if let Some(f) = callbacks.on_candidates_updated {
+                    f(user_data, *count);
+                }
+            }
+            Self::Commit(text) => {
+                if let Some(f) = callbacks.on_commit {
+                    f(user_data, text.as_ptr(), text.as_bytes().len());
+                }
+            }
+            Self::PassThrough(keystroke) => {
+                if let Some(f) = callbacks.on_pass_through {
+                    f(user_data, keystroke);
+                }
+            }
+        }
+    }
+}
+
+/// Thread-safe engine session
+pub(crate) struct Session {
+    state: Mutex<SessionState>,
+    /// Notified when callbacks finish running
+    dispatched: Condvar,
+}
+
+impl Session {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(SessionState {
+                engine: Engine::new(),
+                last_commit: String::new(),
+                callbacks: None,
+                dispatching: None,
+            }),
+            dispatched: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionState> {
+        // A panic while locked is already reported as an error; keep serving
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether callbacks are currently running on the calling thread
+    pub(crate) fn is_dispatching(&self) -> bool {
+        self.lock().dispatching == Some(thread::current().id())
+    }
+
+    /// Apply a modification, then deliver the notifications it produced
+    ///
+    /// Refused with `Busy` from inside a callback; other threads wait for
+    /// running callbacks to finish first. The lock is released before any
+    /// callback is called.
+    fn update<F>(&self, f: F) -> HipKeyResult
+    where
+        F: FnOnce(&mut SessionState) -> Result<Vec<Notification>, HipKeyResult>,
+    {
+        let (notifications, callbacks) = {
+            let current = thread::current().id();
+            let state = self.lock();
+            if state.dispatching == Some(current) {
+                return HipKeyResult::Busy;
+            }
+            let mut state = self
+                .dispatched
+                .wait_while(state, |state| state.dispatching.is_some())
+                .unwrap_or_else(PoisonError::into_inner);
+            let notifications = match f(&mut state) {
+                Ok(notifications) => notifications,
+                Err(code) => return code,
+            };
+            if state.callbacks.is_some() && !notifications.is_empty() {
+                state.dispatching = Some(current);
+            }
+            (notifications, state.callbacks)
+        };
+
+        if let Some(Callbacks(callbacks)) = callbacks {
+            // `extern "C"` callbacks cannot unwind, so the flag is always reset
+            for notification in &notifications {
+                // SAFETY: the host vouched for the callbacks when registering them
+                unsafe { notification.deliver(&callbacks) };
+            }
+            self.lock().dispatching = None;
+            self.dispatched.notify_all();
+        }
+        HipKeyResult::Success
+    }
+
+    pub(crate) fn set_callbacks(&self, callbacks: Option<HipKeyCallbacks>) -> HipKeyResult {
+        self.update(|state| {
+            state.callbacks = callbacks.map(Callbacks);
+            Ok(Vec::new())
+        })
+    }
+
+    pub(crate) fn set_language_pack(&self, pack: Arc<dyn LanguagePack>) -> HipKeyResult {
+        self.update(|state| {
+            state.engine.set_shared_language_pack(pack);
+            Ok(Vec::new())
+        })
+    }
+
+    /// Switch language pack even while callbacks run
+    ///
+    /// For registry-wide switches, which do not come from a callback of
+    /// this session and so are not re-entrant.
+    pub(crate) fn replace_language_pack(&self, pack: Arc<dyn LanguagePack>) {
+        self.lock().engine.set_shared_language_pack(pack);
+    }
+
+    /// # Safety
+    /// `keystroke` must point to a valid `HipKeyKeystroke`; `out_events`
+    /// must be null or valid for a write.
+    pub(crate) unsafe fn process_keystroke(&self, keystroke: *const HipKeyKeystroke, out_events: *mut u32) -> HipKeyResult {
+        let Some(c_keystroke) = keystroke.as_ref() else {
+            return HipKeyResult::InvalidArgument;
+        };
+        let Some(ks) = to_keystroke(c_keystroke) else {
+            return HipKeyResult::InvalidArgument;
+        };
+        self.update(|state| {
+            if state.engine.language_pack_id().is_none() {
+                return Err(HipKeyResult::NotReady);
+            }
+
+            let events = state.engine.process(&ks);
+            let mut mask = 0;
+            for event in &events {
+                mask |= match event {
+                    EngineEvent::BufferChanged => HIPKEY_EVENT_BUFFER_CHANGED,
+                    EngineEvent::CandidatesUpdated => HIPKEY_EVENT_CANDIDATES_UPDATED,
+                    EngineEvent::Commit(text) => {
+                        state.last_commit = text.clone();
+                        HIPKEY_EVENT_COMMIT
+                    }
+                    EngineEvent::PassThrough => HIPKEY_EVENT_PASS_THROUGH,
+                };
+            }
+            if !out_events.is_null() {
+                *out_events = mask;
+            }
+            Ok(events
+                .iter()
+                .map(|event| Notification::from_event(event, &state.engine, c_keystroke))
+                .collect())
+        })
+    }
+
+    pub(crate) fn commit(&self) -> HipKeyResult {
+        self.update(|state| {
+            state.last_commit = state.engine.commit();
+            if state.last_commit.is_empty() {
+                return Ok(Vec::new());
+            }
+            Ok(vec![
+                Notification::Commit(Notification::text(&state.last_commit)),
+                Notification::Preedit(CString::default()),
+            ])
+        })
+    }
+
+    pub(crate) fn clear(&self) -> HipKeyResult {
+        self.update(|state| {
+            state.engine.clear();
+            Ok(vec![Notification::Preedit(CString::default())])
+        })
+    }
+
+    /// # Safety
+    /// See `write_str`.
+    pub(crate) unsafe fn composing_text(&self, buf: *mut c_char, capacity: usize, out_len: *mut usize) -> HipKeyResult {
+        write_str(self.lock().engine.buffer().composing(), buf, capacity, out_len)
+    }
+
+    /// # Safety
+    /// See `write_str`.
+    pub(crate) unsafe fn committed_text(&self, buf: *mut c_char, capacity: usize, out_len: *mut usize) -> HipKeyResult {
+        write_str(self.lock().engine.buffer().committed(), buf, capacity, out_len)
+    }
+
+    /// # Safety
+    /// See `write_str`.
+    pub(crate) unsafe fn last_commit_text(&self, buf: *mut c_char, capacity: usize, out_len: *mut usize) -> HipKeyResult {
+        write_str(&self.lock().last_commit, buf, capacity, out_len)
+    }
+
+    /// # Safety
+    /// `out_composing` must be null or valid for a write.
+    pub(crate) unsafe fn is_composing(&self, out_composing: *mut bool) -> HipKeyResult {
+        match out_composing.as_mut() {
+            Some(out) => {
+                *out = !self.lock().engine.is_idle();
+                HipKeyResult::Success
+            }
+            None => HipKeyResult::InvalidArgument,
+        }
+    }
+
+    /// # Safety
+    /// `out_count` must be null or valid for a write.
+    pub(crate) unsafe fn candidate_count(&self, out_count: *mut usize) -> HipKeyResult {
+        match out_count.as_mut() {
+            Some(out) => {
+                *out = self.lock().engine.candidates().len();
+                HipKeyResult::Success
+            }
+            None => HipKeyResult::InvalidArgument,
+        }
+    }
+
+    /// # Safety
+    /// See `write_str`.
+    pub(crate) unsafe fn candidate(&self, index: usize, buf: *mut c_char, capacity: usize, out_len: *mut usize) -> HipKeyResult {
+        match self.lock().engine.candidates().get(index) {
+            Some(candidate) => write_str(&candidate.text, buf, capacity, out_len),
+            None => HipKeyResult::InvalidArgument,
+        }
+    }
+
+    /// # Safety
+    /// `out_info` must be null or valid for a write.
+    pub(crate) unsafe fn candidate_info(&self, index: usize, out_info: *mut HipKeyCandidateInfo) -> HipKeyResult {
+        let state = self.lock();
+        match (state.engine.candidates().get(index), out_info.as_mut()) {
+            (Some(candidate), Some(out)) => {
+                *out = HipKeyCandidateInfo::from(candidate);
+                HipKeyResult::Success
+            }
+            _ => HipKeyResult::InvalidArgument,
+        }
+    }
+
+    /// Feedback leaves the engine as it is, so callbacks may send it
+    pub(crate) fn candidate_feedback(&self, candidate_id: u64, accepted: bool) -> HipKeyResult {
+        let feedback = if accepted { Feedback::Accepted } else { Feedback::Rejected };
+        if self.lock().engine.candidate_feedback(candidate_id, feedback) {
+            HipKeyResult::Success
+        } else {
+            HipKeyResult::InvalidArgument
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! ABI checks for the C API
+//!
+//! - `include/hipkey.h` declares exactly the symbols exported from `src/lib.rs`
+//! - a C program compiled against the header links against the `staticlib`
+//!   and `cdylib` outputs and drives a Telex session
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e))
+}
+
+/// Contents of every `.rs` file under `src/`
+fn sources() -> Vec<String> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(crate_dir().join("src"))
+        .expect("reading src/")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| read(path)).collect()
+}
+
+/// `extern "C"` functions marked `#[no_mangle]` in the crate source
+fn exported_functions() -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for source in sources() {
+        collect_exports(&source, &mut names);
+    }
+    names
+}
+
+fn collect_exports(source: &str, names: &mut BTreeSet<String>) {
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() != "#[no_mangle]" {
+            continue;
+        }
+        let decl = lines.next().expect("item after #[no_mangle]");
+        let name = decl
+            .split("fn ")
+            .nth(1)
+            .and_then(|rest| rest.split('(').next())
+            .unwrap_or_else(|| panic!("unexpected item after #[no_mangle]: {}", decl));
+        names.insert(name.trim().to_string());
+    }
+}
+
+/// Function names declared in the header
+fn header_functions(header: &str) -> BTreeSet<String> {
+    header
+        .lines()
+        .filter(|line| !line.starts_with("//") && !line.starts_with('#'))
+        .filter_map(|line| {
+            let name = line.split('(').next()?.rsplit([' ', '*']).next()?;
+            name.starts_with("hipkey_").then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Value of a `pub const NAME: u32 = VALUE;` in the crate source
+fn rust_const(name: &str) -> String {
+    let source = read(&crate_dir().join("src/lib.rs"));
+    let prefix = format!("pub const {}: u32 = ", name);
+    source
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .and_then(|rest| rest.strip_suffix(';'))
+        .unwrap_or_else(|| panic!("const {} not found", name))
+        .to_string()
+}
+
+/// Directory holding freshly built `staticlib` and `cdylib` outputs
+///
+/// `cargo test` only builds what the test harness links, so the C outputs
+/// are built here, in a separate target dir to stay clear of the outer
+/// cargo's build lock.
+fn artifact_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        // Test binaries live in target/<profile>/deps
+        let exe = std::env::current_exe().expect("current_exe");
+        let target = exe.ancestors().nth(3).expect("target dir").join("ffi-abi");
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let status = Command::new(cargo)
+            .current_dir(crate_dir())
+            .args(["build", "--quiet", "--lib", "--target-dir"])
+            .arg(&target)
+            .status()
+            .expect("running cargo build");
+        assert!(status.success(), "building hip-key-ffi failed");
+        target.join("debug")
+    })
+}
+
+#[test]
+fn test_header_matches_exports() {
+    let header = read(&crate_dir().join("include/hipkey.h"));
+    assert_eq!(
+        header_functions(&header),
+        exported_functions(),
+        "include/hipkey.h is stale; regenerate it with cbindgen (see cbindgen.toml)"
+    );
+}
+
+#[test]
+fn test_header_abi_version() {
+    let header = read(&crate_dir().join("include/hipkey.h"));
+    for name in ["HIPKEY_ABI_VERSION_MAJOR", "HIPKEY_ABI_VERSION_MINOR"] {
+        let define = format!("#define {} {}", name, rust_const(name));
+        assert!(header.contains(&define), "header is missing `{}`", define);
+    }
+}
+
+#[test]
+fn test_header_is_up_to_date() {
+    // Byte-for-byte check when cbindgen is installed
+    let out = std::env::temp_dir().join(format!("hipkey-{}.h", std::process::id()));
+    let status = Command::new("cbindgen")
+        .current_dir(crate_dir())
+        .args(["--config", "cbindgen.toml", "--quiet", "--output"])
+        .arg(&out)
+        .status();
+    let Ok(status) = status else {
+        eprintln!("cbindgen not found; skipping");
+        return;
+    };
+    assert!(status.success(), "cbindgen failed");
+    let generated = read(&out);
+    let _ = std::fs::remove_file(&out);
+    assert_eq!(
+        generated,
+        read(&crate_dir().join("include/hipkey.h")),
+        "include/hipkey.h is stale; regenerate it with cbindgen (see cbindgen.toml)"
+    );
+}
+
+#[cfg(unix)]
+fn compile_and_run(name: &str, link_args: &[&str]) {
+    let dir = artifact_dir();
+    let exe = dir.join(name);
+    let status = Command::new("cc")
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(crate_dir().join("include"))
+        .arg(crate_dir().join("tests/c/telex_session.c"))
+        .arg("-o")
+        .arg(&exe)
+        .args(link_args)
+        .status();
+    let Ok(status) = status else {
+        eprintln!("no C compiler found; skipping");
+        return;
+    };
+    assert!(status.success(), "compiling tests/c/telex_session.c failed");
+
+    let output = Command::new(&exe).output().expect("running C test program");
+    assert!(
+        output.status.success(),
+        "C test program failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "ok");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_c_program_staticlib() {
+    let lib = artifact_dir().join("libhip_key_ffi.a");
+    let lib = lib.to_str().expect("utf-8 path");
+    compile_and_run("telex_session_static", &[lib, "-lpthread", "-ldl", "-lm"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_c_program_cdylib() {
+    let dir = artifact_dir();
+    let dir = dir.to_str().expect("utf-8 path");
+    let rpath = format!("-Wl,-rpath,{}", dir);
+    compile_and_run("telex_session_dynamic", &["-L", dir, "-lhip_key_ffi", &rpath]);
+}
@@ -0,0 +1,152 @@
+/* Drives a Telex typing session through the public C API. */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "hipkey.h"
+
+#define CHECK(expr)                                                        \
+    do {                                                                   \
+        if (!(expr)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,         \
+                    __LINE__, #expr);                                      \
+            exit(1);                                                       \
+        }                                                                  \
+    } while (0)
+
+struct session_log {
+    char preedit[64];
+    char commit[64];
+    int commits;
+};
+
+static void on_preedit(void *user_data, const char *text, size_t len) {
+    struct session_log *log = user_data;
+    CHECK(len < sizeof log->preedit && strlen(text) == len);
+    memcpy(log->preedit, text, len + 1);
+}
+
+static void on_commit(void *user_data, const char *text, size_t len) {
+    struct session_log *log = user_data;
+    CHECK(len < sizeof log->commit);
+    memcpy(log->commit, text, len + 1);
+    log->commits++;
+}
+
+static void type_text(HipKeyEngine *engine, const char *text) {
+    for (const char *p = text; *p; p++) {
+        HipKeyKeystroke ks = {HIP_KEY_KEY_CHAR, (uint32_t)*p, 0};
+        uint32_t events = 0;
+        CHECK(hipkey_process_keystroke(engine, &ks, &events) == HIP_KEY_RESULT_SUCCESS);
+        CHECK(events & HIPKEY_EVENT_BUFFER_CHANGED);
+    }
+}
+
+static void expect_composing(HipKeyEngine *engine, const char *expected) {
+    char buf[64];
+    size_t len = 0;
+    CHECK(hipkey_get_composing_text(engine, NULL, 0, &len) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(len == strlen(expected));
+    CHECK(hipkey_get_composing_text(engine, buf, sizeof buf, &len) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(strcmp(buf, expected) == 0);
+}
+
+static void commit_and_expect(HipKeyEngine *engine, const char *expected) {
+    char buf[64];
+    CHECK(hipkey_commit(engine) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_get_last_commit_text(engine, buf, sizeof buf, NULL) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(strcmp(buf, expected) == 0);
+}
+
+int main(void) {
+    uint32_t version = hipkey_abi_version();
+    CHECK((version >> 16) == HIPKEY_ABI_VERSION_MAJOR);
+    CHECK((version & 0xffff) >= HIPKEY_ABI_VERSION_MINOR);
+
+    HipKeyEngine *engine = hipkey_engine_create();
+    CHECK(engine != NULL);
+
+    HipKeyKeystroke a = {HIP_KEY_KEY_CHAR, 'a', 0};
+    CHECK(hipkey_process_keystroke(engine, &a, NULL) == HIP_KEY_RESULT_NOT_READY);
+    CHECK(hipkey_engine_set_language_pack(engine, "vi", "telex") == HIP_KEY_RESULT_SUCCESS);
+
+    struct session_log log = {{0}, {0}, 0};
+    HipKeyCallbacks callbacks = {&log, on_preedit, NULL, on_commit, NULL};
+    CHECK(hipkey_engine_set_callbacks(engine, &callbacks) == HIP_KEY_RESULT_SUCCESS);
+
+    type_text(engine, "vieets");
+    expect_composing(engine, "vi\xe1\xba\xbft"); /* viết */
+    CHECK(strcmp(log.preedit, "vi\xe1\xba\xbft") == 0);
+
+    bool composing = false;
+    CHECK(hipkey_is_composing(engine, &composing) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(composing);
+    commit_and_expect(engine, "vi\xe1\xba\xbft");
+
+    type_text(engine, "nawmm");
+    HipKeyKeystroke backspace = {HIP_KEY_KEY_BACKSPACE, 0, 0};
+    CHECK(hipkey_process_keystroke(engine, &backspace, NULL) == HIP_KEY_RESULT_SUCCESS);
+    expect_composing(engine, "n\xc4\x83m"); /* năm */
+    commit_and_expect(engine, "n\xc4\x83m");
+    CHECK(log.commits == 2);
+    CHECK(strcmp(log.commit, "n\xc4\x83m") == 0);
+    CHECK(log.preedit[0] == '\0');
+
+    char tiny[2];
+    size_t len = 0;
+    CHECK(hipkey_get_committed_text(engine, tiny, sizeof tiny, &len) == HIP_KEY_RESULT_BUFFER_TOO_SMALL);
+    CHECK(len == strlen("vi\xe1\xba\xbft" "n\xc4\x83m"));
+
+    size_t count = 1;
+    CHECK(hipkey_get_candidate_count(engine, &count) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(count == 0);
+
+    CHECK(hipkey_clear(engine) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_is_composing(engine, &composing) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(!composing);
+
+    hipkey_engine_destroy(engine);
+
+    /* Candidate ids come back as feedback after a selection */
+    engine = hipkey_engine_create();
+    CHECK(hipkey_engine_set_language_pack(engine, "vi", "accentless") == HIP_KEY_RESULT_SUCCESS);
+    type_text(engine, "viet");
+    CHECK(hipkey_get_candidate_count(engine, &count) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(count > 1);
+    HipKeyCandidateInfo info;
+    CHECK(hipkey_get_candidate_info(engine, 1, &info) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(info.source == HIP_KEY_CANDIDATE_SOURCE_DICTIONARY);
+    CHECK(info.kind == HIP_KEY_CANDIDATE_KIND_PHRASE); /* Việt Nam */
+    HipKeyKeystroke two = {HIP_KEY_KEY_CHAR, '2', 0};
+    uint32_t events = 0;
+    CHECK(hipkey_process_keystroke(engine, &two, &events) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(events == (HIPKEY_EVENT_COMMIT | HIPKEY_EVENT_BUFFER_CHANGED));
+    CHECK(hipkey_candidate_feedback(engine, info.id, true) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_candidate_feedback(engine, info.id + 1, false) == HIP_KEY_RESULT_INVALID_ARGUMENT);
+    hipkey_engine_destroy(engine);
+
+    /* Two input contexts sharing one language pack */
+    HipKeyRegistry *registry = hipkey_registry_create();
+    CHECK(registry != NULL);
+    CHECK(hipkey_registry_set_language_pack(registry, "vi", "telex") == HIP_KEY_RESULT_SUCCESS);
+    HipKeySessionId first = 0, second = 0;
+    CHECK(hipkey_session_create(registry, &first) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_session_create(registry, &second) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(first != second);
+    HipKeyKeystroke o = {HIP_KEY_KEY_CHAR, 'o', 0};
+    CHECK(hipkey_session_process_keystroke(registry, first, &o, NULL) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_session_process_keystroke(registry, first, &o, NULL) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_session_process_keystroke(registry, second, &o, NULL) == HIP_KEY_RESULT_SUCCESS);
+    char text[16];
+    CHECK(hipkey_session_get_composing_text(registry, first, text, sizeof text, NULL) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(strcmp(text, "\xc3\xb4") == 0); /* ô */
+    CHECK(hipkey_session_get_composing_text(registry, second, text, sizeof text, NULL) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(strcmp(text, "o") == 0);
+    CHECK(hipkey_session_destroy(registry, first) == HIP_KEY_RESULT_SUCCESS);
+    CHECK(hipkey_session_commit(registry, first) == HIP_KEY_RESULT_INVALID_ARGUMENT);
+    hipkey_registry_destroy(registry);
+
+    puts("ok");
+    return 0;
+}
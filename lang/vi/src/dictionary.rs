@@ -0,0 +1,334 @@
+//! Vietnamese word and syllable dictionary
+//!
+//! The bundled list is `data/vi.dict`, parsed on first use. Each line holds
+//! a word or phrase, a tab and its frequency; [`Dictionary::from_word_list`]
+//! and [`Dictionary::to_file`] regenerate it from a plain word list.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::{CharInfo, ToneMark, VowelMod};
+
+/// Comment [`Dictionary::to_file`] starts the file with
+const HEADER: &str = "\
+# hip-key Vietnamese dictionary
+#
+# One word or phrase per line: text, a tab, then its frequency (higher is
+# more common), most frequent first. Lines starting with `#` are comments.
+#
+# Regenerate from a word list (one word or phrase per line, optionally
+# followed by a tab and a count) with:
+#   hip-key dict <word-list> lang/vi/data/vi.dict
+";
+
+/// A dictionary word or phrase
+#[derive(Debug, Clone)]
+pub struct Entry {
+    text: String,
+    frequency: u32,
+    /// Lowercase letters, without the spaces between syllables
+    letters: Vec<CharInfo>,
+    /// Tone of the syllable each letter belongs to
+    tones: Vec<ToneMark>,
+    /// `letters` without any marks (`việt nam` → `vietnam`)
+    skeleton: String,
+}
+
+impl Entry {
+    fn new(text: &str, frequency: u32) -> Self {
+        let mut letters = Vec::new();
+        let mut tones = Vec::new();
+        for syllable in text.split_whitespace() {
+            let chars = CharInfo::parse(&syllable.to_lowercase());
+            let tone = chars.iter().map(|ch| ch.tone).find(|&t| t != ToneMark::None).unwrap_or(ToneMark::None);
+            tones.extend(chars.iter().map(|_| tone));
+            letters.extend(chars);
+        }
+        let skeleton = letters.iter().map(|ch| ch.base).collect();
+        Self { text: text.to_string(), frequency, letters, tones, skeleton }
+    }
+
+    /// The word or phrase as written (`Việt Nam`)
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// How common it is; only the order between entries matters
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Whether this could be what `typed` is the beginning of
+    ///
+    /// Letters must agree, and so must each mark already typed; a letter
+    /// typed without marks may still get them. A tone counts for its whole
+    /// syllable, since it moves while the syllable is typed (`tié` fits
+    /// `tiến`).
+    fn continues(&self, typed: &[CharInfo]) -> bool {
+        typed.len() <= self.letters.len()
+            && typed.iter().zip(&self.letters).zip(&self.tones).all(|((typed, letter), &tone)| {
+                typed.base == letter.base
+                    && (typed.vowel_mod == VowelMod::None || typed.vowel_mod == letter.vowel_mod)
+                    && (typed.tone == ToneMark::None || typed.tone == tone)
+            })
+    }
+}
+
+/// Words and phrases with their frequencies
+#[derive(Debug, Clone, Default)]
+pub struct Dictionary {
+    /// Sorted by skeleton, for prefix lookups
+    entries: Vec<Entry>,
+    /// Sum of the frequencies
+    total: u64,
+}
+
+impl Dictionary {
+    /// The dictionary bundled with the pack, loaded on first use
+    pub fn bundled() -> &'static Dictionary {
+        static BUNDLED: OnceLock<Dictionary> = OnceLock::new();
+        BUNDLED.get_or_init(|| Dictionary::parse(include_str!("../data/vi.dict")))
+    }
+
+    fn from_entries(mut entries: Vec<Entry>) -> Self {
+        entries.sort_by(|a, b| a.skeleton.cmp(&b.skeleton));
+        let total = entries.iter().map(|entry| u64::from(entry.frequency)).sum();
+        Self { entries, total }
+    }
+
+    /// Read the dictionary file format: `text<TAB>frequency` per line
+    ///
+    /// Blank lines, `#` comments and lines without a frequency are skipped.
+    pub fn parse(data: &str) -> Self {
+        let entries = data
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (text, frequency) = line.split_once('\t')?;
+                Some(Entry::new(text.trim(), frequency.trim().parse().ok()?))
+            })
+            .collect();
+        Self::from_entries(entries)
+    }
+
+    /// Build a dictionary from a word list
+    ///
+    /// Each line is a word or phrase, optionally followed by a tab and a
+    /// count; counts of repeated lines add up, and a line without one
+    /// counts once.
+    pub fn from_word_list(list: &str) -> Self {
+        let mut counts: HashMap<&str, u32> = HashMap::new();
+        for line in list.lines() {
+            let (text, count) = match line.split_once('\t') {
+                Some((text, count)) => (text.trim(), count.trim().parse().unwrap_or(1)),
+                None => (line.trim(), 1),
+            };
+            if !text.is_empty() {
+                let total = counts.entry(text).or_default();
+                *total = total.saturating_add(count);
+            }
+        }
+        Self::from_entries(counts.into_iter().map(|(text, count)| Entry::new(text, count)).collect())
+    }
+
+    /// Write the dictionary file format, most frequent first
+    pub fn to_file(&self) -> String {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| b.frequency.cmp(&a.frequency).then_with(|| a.text.cmp(&b.text)));
+        let lines = entries.iter().map(|entry| format!("{}\t{}\n", entry.text, entry.frequency));
+        std::iter::once(HEADER.to_string()).chain(lines).collect()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries that `typed` could be the beginning of, most frequent first
+    ///
+    /// Spaces in `typed` are ignored, so a run-together `xinchao` finds
+    /// `xin chào`.
+    pub fn completions(&self, typed: &str) -> Vec<&Entry> {
+        self.find(typed, false)
+    }
+
+    /// Every way of writing `typed` with the marks it lacks, most frequent first
+    ///
+    /// Like [`completions`](Self::completions) but without longer entries:
+    /// `viet` gives `việt` and `viết`, `viet nam` gives `Việt Nam`.
+    pub fn accented(&self, typed: &str) -> Vec<&Entry> {
+        self.find(typed, true)
+    }
+
+    /// Ways of reading `typed`, typed without marks, best first with a score
+    ///
+    /// Entries it begins score their frequency: `viet` gives `việt`,
+    /// `Việt Nam`, `viết`. Text of several words is also read in parts,
+    /// the last one in each of its forms (`toi yeu viet` → `tôi yêu việt`,
+    /// `tôi yêu viết`...). Such a guess scores the frequency it would have
+    /// if its parts were independent, so known phrases come first.
+    pub fn readings(&self, typed: &str) -> Vec<(String, f64)> {
+        let mut readings: Vec<(String, f64)> = self
+            .completions(typed)
+            .into_iter()
+            .map(|entry| (entry.text.clone(), f64::from(entry.frequency)))
+            .collect();
+
+        let words: Vec<&str> = typed.split_whitespace().collect();
+        for split in 1..words.len() {
+            let (start, frequency) = self.read_words(&words[..split]);
+            for entry in self.completions(&words[split..].join(" ")) {
+                let score = frequency * self.share(entry.frequency);
+                readings.push((format!("{} {}", start, entry.text), score));
+            }
+        }
+
+        // Stable, so equal scores keep the order of the last part's frequency
+        readings.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut seen = HashSet::new();
+        readings.retain(|(text, _)| seen.insert(text.clone()));
+        readings
+    }
+
+    /// Share of all the frequencies that `frequency` is
+    fn share(&self, frequency: u32) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            f64::from(frequency) / self.total as f64
+        }
+    }
+
+    /// Most frequent reading of `words`, longest known phrases first, and
+    /// its estimated frequency (0 if a word is unknown)
+    fn read_words(&self, words: &[&str]) -> (String, f64) {
+        let mut parts = Vec::new();
+        let mut frequency = None;
+        let mut i = 0;
+        while i < words.len() {
+            let found = (i + 1..=words.len())
+                .rev()
+                .find_map(|j| self.accented(&words[i..j].join(" ")).first().map(|&entry| (entry, j)));
+            match found {
+                Some((entry, j)) => {
+                    parts.push(entry.text.as_str());
+                    frequency = Some(match frequency {
+                        None => f64::from(entry.frequency),
+                        Some(before) => before * self.share(entry.frequency),
+                    });
+                    i = j;
+                }
+                None => {
+                    parts.push(words[i]);
+                    frequency = Some(0.0);
+                    i += 1;
+                }
+            }
+        }
+        (parts.join(" "), frequency.unwrap_or_default())
+    }
+
+    fn find(&self, typed: &str, whole: bool) -> Vec<&Entry> {
+        let typed: Vec<CharInfo> =
+            CharInfo::parse(&typed.to_lowercase()).into_iter().filter(|ch| !ch.base.is_whitespace()).collect();
+        if typed.is_empty() {
+            return Vec::new();
+        }
+        let skeleton: String = typed.iter().map(|ch| ch.base).collect();
+
+        let start = self.entries.partition_point(|entry| entry.skeleton < skeleton);
+        let mut found: Vec<&Entry> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.skeleton.starts_with(&skeleton))
+            .filter(|entry| (!whole || entry.letters.len() == typed.len()) && entry.continues(&typed))
+            .collect();
+        found.sort_by(|a, b| b.frequency.cmp(&a.frequency).then_with(|| a.text.cmp(&b.text)));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(entries: Vec<&Entry>) -> Vec<&str> {
+        entries.into_iter().map(Entry::text).collect()
+    }
+
+    #[test]
+    fn test_bundled_loads() {
+        let dictionary = Dictionary::bundled();
+        assert!(dictionary.len() > 200);
+        assert!(std::ptr::eq(dictionary, Dictionary::bundled()));
+    }
+
+    #[test]
+    fn test_completions() {
+        let dictionary = Dictionary::bundled();
+        let vie = texts(dictionary.completions("vie"));
+        let words: Vec<&str> = vie.iter().copied().filter(|text| !text.contains(' ')).collect();
+        assert_eq!(words[..2], ["việt", "viên"]);
+        assert!(words[..4].contains(&"viện"));
+        assert!(vie.contains(&"Việt Nam"));
+        // Marks already typed narrow the list
+        assert_eq!(texts(dictionary.completions("việ")), ["việt", "Việt Nam", "việc", "viện"]);
+        assert_eq!(texts(dictionary.completions("viến")), ["viếng"]);
+        // The tone may still sit elsewhere in the syllable
+        assert!(texts(dictionary.completions("tié")).contains(&"tiến"));
+        // Phrases, with or without the space
+        assert!(texts(dictionary.completions("xinchao")).contains(&"xin chào"));
+        assert_eq!(texts(dictionary.completions("Việt N")), ["Việt Nam"]);
+        assert!(dictionary.completions("").is_empty());
+        assert!(dictionary.completions("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_accented() {
+        let dictionary = Dictionary::bundled();
+        assert_eq!(texts(dictionary.accented("viet")), ["việt", "viết"]);
+        assert_eq!(texts(dictionary.accented("viet nam")), ["Việt Nam"]);
+        assert_eq!(texts(dictionary.accented("hoa")), ["hoa", "hòa"]);
+        assert!(dictionary.accented("vie").is_empty());
+    }
+
+    #[test]
+    fn test_readings() {
+        let dictionary = Dictionary::bundled();
+        let readings = |typed| dictionary.readings(typed).into_iter().map(|(text, _)| text).collect::<Vec<_>>();
+        assert_eq!(readings("viet")[..3], ["việt", "Việt Nam", "viết"]);
+        // A known phrase beats reading it word by word
+        assert_eq!(readings("viet nam")[0], "Việt Nam");
+        assert_eq!(readings("toi yeu viet nam")[0], "tôi yêu Việt Nam");
+        assert_eq!(readings("toi yeu viet")[..2], ["tôi yêu việt", "tôi yêu Việt Nam"]);
+        assert_eq!(readings("tieng viet")[0], "tiếng Việt");
+        // Unknown words are kept as typed
+        assert_eq!(readings("abc viet")[0], "abc việt");
+        let scores: Vec<f64> = dictionary.readings("toi di").into_iter().map(|(_, score)| score).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_word_list_round_trip() {
+        let dictionary = Dictionary::from_word_list("việt\nviên\nviệt\nViệt Nam\t5\n\n");
+        assert_eq!(dictionary.to_file(), format!("{}Việt Nam\t5\nviệt\t2\nviên\t1\n", HEADER));
+        let reread = Dictionary::parse(&dictionary.to_file());
+        assert_eq!(reread.to_file(), dictionary.to_file());
+    }
+
+    #[test]
+    fn test_word_list_counts_saturate() {
+        let dictionary = Dictionary::from_word_list("việt\t4294967295\nviệt\t4294967295\n");
+        assert_eq!(dictionary.to_file(), format!("{}việt\t{}\n", HEADER, u32::MAX));
+    }
+
+    #[test]
+    fn test_bundled_file_is_regenerable() {
+        // The shipped file is exactly what `to_file` writes
+        assert_eq!(Dictionary::bundled().to_file(), include_str!("../data/vi.dict"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Legacy Vietnamese encodings
+//!
+//! Before Unicode, Vietnamese text was stored in 8-bit encodings made for
+//! specific fonts, and many documents still are. [`Encoding::encode`] and
+//! [`Encoding::decode`] convert committed text to and from them.
+
+use std::collections::HashMap;
+
+use crate::{CharInfo, ToneMark, VowelMod, VOWELS};
+
+/// TCVN3 codes for each row of `VOWELS`, one column per `ToneMark`
+///
+/// `0` marks the plain ASCII letter.
+const TCVN3_VOWELS: [[u8; 6]; 12] = [
+    [0, 0xB8, 0xB5, 0xB6, 0xB7, 0xB9],       // a á à ả ã ạ
+    [0xA8, 0xBE, 0xBB, 0xBC, 0xBD, 0xC6],    // ă ắ ằ ẳ ẵ ặ
+    [0xA9, 0xCA, 0xC7, 0xC8, 0xC9, 0xCB],    // â ấ ầ ẩ ẫ ậ
+    [0, 0xD0, 0xCC, 0xCE, 0xCF, 0xD1],       // e é è ẻ ẽ ẹ
+    [0xAA, 0xD5, 0xD2, 0xD3, 0xD4, 0xD6],    // ê ế ề ể ễ ệ
+    [0, 0xDD, 0xD7, 0xD8, 0xDC, 0xDE],       // i í ì ỉ ĩ ị
+    [0, 0xE3, 0xDF, 0xE1, 0xE2, 0xE4],       // o ó ò ỏ õ ọ
+    [0xAB, 0xE8, 0xE5, 0xE6, 0xE7, 0xE9],    // ô ố ồ ổ ỗ ộ
+    [0xAC, 0xED, 0xEA, 0xEB, 0xEC, 0xEE],    // ơ ớ ờ ở ỡ ợ
+    [0, 0xF3, 0xEF, 0xF1, 0xF2, 0xF4],       // u ú ù ủ ũ ụ
+    [0xAD, 0xF8, 0xF5, 0xF6, 0xF7, 0xF9],    // ư ứ ừ ử ữ ự
+    [0, 0xFD, 0xFA, 0xFB, 0xFC, 0xFE],       // y ý ỳ ỷ ỹ ỵ
+];
+
+/// TCVN3 codes for the only capitals it has
+const TCVN3_CAPITALS: [(char, u8); 7] = [
+    ('Ă', 0xA1),
+    ('Â', 0xA2),
+    ('Ê', 0xA3),
+    ('Ô', 0xA4),
+    ('Ơ', 0xA5),
+    ('Ư', 0xA6),
+    ('Đ', 0xA7),
+];
+
+/// Legacy encoding of Vietnamese text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// TCVN 5712:1993 VN3, used by the ABC fonts (`.VnTime`)
+    ///
+    /// It has no capitals with tone marks: those are written with the
+    /// lowercase codes, which the capital fonts (`.VnTimeH`) show as
+    /// capitals, and so decode as lowercase.
+    Tcvn3,
+    /// VNI-Windows: a Latin-1 letter followed by up to two mark bytes,
+    /// used by the VNI fonts (`VNI-Times`)
+    VniWindows,
+}
+
+impl Encoding {
+    /// Every encoding
+    pub const ALL: [Encoding; 2] = [Encoding::Tcvn3, Encoding::VniWindows];
+
+    /// Identifier used on the command line (`"vni-windows"`)
+    pub fn id(self) -> &'static str {
+        match self {
+            Encoding::Tcvn3 => "tcvn3",
+            Encoding::VniWindows => "vni-windows",
+        }
+    }
+
+    /// Look up an encoding by its [`id`](Self::id)
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|encoding| encoding.id() == id)
+    }
+
+    /// Display name (`"TCVN3 (ABC)"`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Tcvn3 => "TCVN3 (ABC)",
+            Encoding::VniWindows => "VNI-Windows",
+        }
+    }
+
+    /// Bytes for one Vietnamese letter, or `None` if it has no code
+    fn code(self, ch: &CharInfo) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Tcvn3 => tcvn3_code(ch),
+            Encoding::VniWindows => Some(vni_windows_code(ch)),
+        }
+    }
+
+    /// Every Vietnamese letter with its bytes, lowercase first
+    fn codes(self) -> Vec<(char, Vec<u8>)> {
+        let mut letters: Vec<CharInfo> = VOWELS
+            .iter()
+            .flat_map(|&(base, vowel_mod, _)| {
+                ToneMark::ALL.into_iter().map(move |tone| CharInfo { vowel_mod, tone, ..CharInfo::new(base) })
+            })
+            .collect();
+        letters.push(CharInfo::new('đ'));
+        let capitals: Vec<CharInfo> = letters.iter().map(|ch| CharInfo { upper: true, ..ch.clone() }).collect();
+        letters.extend(capitals);
+
+        letters
+            .iter()
+            .filter_map(|ch| self.code(ch).map(|code| (ch.with_tone(ch.tone), code)))
+            .collect()
+    }
+
+    /// Convert Unicode text to this encoding
+    ///
+    /// ASCII is kept as is; characters the encoding lacks become `?`.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let codes: HashMap<char, Vec<u8>> = self.codes().into_iter().collect();
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match codes.get(&c) {
+                Some(code) => out.extend(code),
+                None if c.is_ascii() => out.push(c as u8),
+                None => out.push(b'?'),
+            }
+        }
+        out
+    }
+
+    /// Convert text in this encoding to Unicode
+    ///
+    /// Bytes that don't stand for anything become U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        // Where two letters share a code (TCVN3 capitals), the first wins
+        let mut letters: HashMap<Vec<u8>, char> = HashMap::new();
+        for (c, code) in self.codes() {
+            letters.entry(code).or_insert(c);
+        }
+
+        let mut out = String::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            // Longest match first: VNI-Windows `a` + `ù` is one letter
+            let found = (1..=2).rev().find_map(|len| {
+                let code = bytes.get(i..i + len)?;
+                letters.get(code).map(|&c| (c, len))
+            });
+            let (c, len) = match found {
+                Some(found) => found,
+                None if bytes[i].is_ascii() => (char::from(bytes[i]), 1),
+                None => (char::REPLACEMENT_CHARACTER, 1),
+            };
+            out.push(c);
+            i += len;
+        }
+        out
+    }
+}
+
+/// TCVN3 code for a letter; toned capitals take the lowercase code
+fn tcvn3_code(ch: &CharInfo) -> Option<Vec<u8>> {
+    if ch.vowel_mod == VowelMod::Stroke {
+        return Some(vec![if ch.upper { 0xA7 } else { 0xAE }]);
+    }
+    let row = VOWELS.iter().position(|&(base, vowel_mod, _)| base == ch.base && vowel_mod == ch.vowel_mod)?;
+    let plain = ch.with_tone(ToneMark::None);
+    if let Some(&(_, code)) = TCVN3_CAPITALS.iter().find(|&&(c, _)| c == plain && ch.tone == ToneMark::None) {
+        return Some(vec![code]);
+    }
+    match TCVN3_VOWELS[row][ch.tone.index()] {
+        0 => Some(vec![plain as u8]),
+        code => Some(vec![code]),
+    }
+}
+
+/// VNI-Windows bytes for a letter
+///
+/// Capitals use the capital letter and capital marks, which are the
+/// lowercase ones less 0x20 in Latin-1.
+fn vni_windows_code(ch: &CharInfo) -> Vec<u8> {
+    let simple_tone = |tone: ToneMark| match tone {
+        ToneMark::None => None,
+        ToneMark::Acute => Some(0xF9),       // ù
+        ToneMark::Grave => Some(0xF8),       // ø
+        ToneMark::HookAbove => Some(0xFB),   // û
+        ToneMark::Tilde => Some(0xF5),       // õ
+        ToneMark::DotBelow => Some(if ch.base == 'y' { 0xEE } else { 0xEF }), // î, ï
+    };
+    let mut code: Vec<u8> = match (ch.base, ch.vowel_mod) {
+        ('d', VowelMod::Stroke) => vec![0xF1],                              // ñ
+        ('i', _) => match ch.tone {
+            ToneMark::None => vec![b'i'],
+            ToneMark::Acute => vec![0xED],                                  // í
+            ToneMark::Grave => vec![0xEC],                                  // ì
+            ToneMark::HookAbove => vec![0xE6],                              // æ
+            ToneMark::Tilde => vec![0xF3],                                  // ó
+            ToneMark::DotBelow => vec![0xF2],                               // ò
+        },
+        ('o', VowelMod::Horn) => [0xF4].into_iter().chain(simple_tone(ch.tone)).collect(), // ô
+        ('u', VowelMod::Horn) => [0xF6].into_iter().chain(simple_tone(ch.tone)).collect(), // ö
+        (base, VowelMod::Circumflex) => vec![base as u8, match ch.tone {
+            ToneMark::None => 0xE2,                                         // â
+            ToneMark::Acute => 0xE1,                                        // á
+            ToneMark::Grave => 0xE0,                                        // à
+            ToneMark::HookAbove => 0xE5,                                    // å
+            ToneMark::Tilde => 0xE3,                                        // ã
+            ToneMark::DotBelow => 0xE4,                                     // ä
+        }],
+        (base, VowelMod::Breve) => vec![base as u8, match ch.tone {
+            ToneMark::None => 0xEA,                                         // ê
+            ToneMark::Acute => 0xE9,                                        // é
+            ToneMark::Grave => 0xE8,                                        // è
+            ToneMark::HookAbove => 0xFA,                                    // ú
+            ToneMark::Tilde => 0xFC,                                        // ü
+            ToneMark::DotBelow => 0xEB,                                     // ë
+        }],
+        (base, _) => [base as u8].into_iter().chain(simple_tone(ch.tone)).collect(),
+    };
+    if ch.upper {
+        for byte in code.iter_mut() {
+            *byte = if byte.is_ascii() { byte.to_ascii_uppercase() } else { *byte - 0x20 };
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every Vietnamese letter, lowercase then capitals
+    fn alphabet() -> String {
+        let lower: String = VOWELS.iter().flat_map(|(_, _, forms)| forms).chain(['đ'].iter()).collect();
+        format!("{}{}", lower, lower.to_uppercase())
+    }
+
+    #[test]
+    fn test_encoding_ids() {
+        for encoding in Encoding::ALL {
+            assert_eq!(Encoding::from_id(encoding.id()), Some(encoding));
+        }
+        // VPS has no verified code table yet
+        assert_eq!(Encoding::from_id("vps"), None);
+    }
+
+    #[test]
+    fn test_tcvn3() {
+        let tcvn3 = Encoding::Tcvn3;
+        assert_eq!(tcvn3.encode("Tiếng Việt"), b"Ti\xD5ng Vi\xD6t");
+        assert_eq!(tcvn3.encode("Đường"), b"\xA7\xAD\xEAng");
+        assert_eq!(tcvn3.decode(b"ng\xAD\xEAi"), "người");
+        // Toned capitals share the lowercase codes
+        assert_eq!(tcvn3.encode("VIỆT"), tcvn3.encode("VIệT"));
+        assert_eq!(tcvn3.decode(&tcvn3.encode("VIỆT")), "VIệT");
+    }
+
+    #[test]
+    fn test_vni_windows() {
+        let vni = Encoding::VniWindows;
+        assert_eq!(vni.encode("Tiếng Việt"), b"Tie\xE1ng Vie\xE4t");
+        assert_eq!(vni.encode("được"), b"\xF1\xF6\xF4\xEFc");
+        assert_eq!(vni.encode("Đà Nẵng"), b"\xD1a\xF8 Na\xFCng");
+        assert_eq!(vni.encode("ĐƯỢC"), b"\xD1\xD6\xD4\xCFC");
+        assert_eq!(vni.encode("lịch sỵ"), b"l\xF2ch sy\xEE");
+        assert_eq!(vni.decode(b"Vie\xE4t Nam"), "Việt Nam");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let texts = ["Hà Nội, ngày 2 tháng 9 năm 1945.", "Quyển sách của Người đọc", "a+b=c; e^x \\ path"];
+        for encoding in Encoding::ALL {
+            for text in texts {
+                assert_eq!(encoding.decode(&encoding.encode(text)), text, "{:?} {}", encoding, text);
+            }
+        }
+        // Every letter
+        let all = alphabet();
+        assert_eq!(Encoding::VniWindows.decode(&Encoding::VniWindows.encode(&all)), all);
+        // TCVN3 writes toned capitals with the lowercase codes, so only
+        // the untoned ones come back as capitals
+        let capitals = "AĂÂEÊIOÔƠUƯYĐ";
+        for c in all.chars() {
+            let expected = if c.is_lowercase() || capitals.contains(c) { c } else { c.to_lowercase().next().unwrap() };
+            assert_eq!(Encoding::Tcvn3.decode(&Encoding::Tcvn3.encode(&c.to_string())), expected.to_string());
+        }
+    }
+
+    #[test]
+    fn test_codes_are_distinct() {
+        for encoding in Encoding::ALL {
+            let codes = encoding.codes();
+            let lower: Vec<&Vec<u8>> = codes.iter().filter(|(c, _)| c.is_lowercase()).map(|(_, code)| code).collect();
+            for (i, code) in lower.iter().enumerate() {
+                assert!(!lower[..i].contains(code), "{:?} {:?}", encoding, code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unmapped() {
+        assert_eq!(Encoding::Tcvn3.encode("€ ü"), b"? ?");
+        assert_eq!(Encoding::Tcvn3.decode(b"\xA0"), "\u{FFFD}");
+    }
+}
@@ -2,26 +2,90 @@
 //!
 //! Input methods: Telex, VNI (extensible)
 
-use hip_key_core::{Keystroke, LanguagePack, ProcessResult, CandidateList, Key};
+use hip_key_core::{Keystroke, LanguagePack, ProcessResult, Candidate, CandidateKind, CandidateList, Key};
+
+pub mod dictionary;
+pub mod encoding;
+pub mod normalization;
+pub mod syllable;
+pub mod viqr;
+
+pub use dictionary::Dictionary;
+pub use encoding::Encoding;
+pub use normalization::{normalize, Normalization};
+pub use syllable::{Syllable, ToneStyle};
 
 /// Vietnamese input method type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum InputMethod {
-    /// Telex input (e.g., aw -> ă, aa -> â)
+    /// Telex input (e.g., aw -> ă, aa -> â); standalone `w`, `[` and `]`
+    /// follow [`Vietnamese::with_telex_shortcuts`]
+    #[default]
     Telex,
+    /// Telex where `w` only modifies a vowel and brackets are punctuation
+    SimpleTelex,
+    /// Telex with standalone `w` -> ư, `[` -> ơ and `]` -> ư
+    Telex2,
     /// VNI input (e.g., a8 -> ă, a6 -> â)
     VNI,
+    /// VIQR input (e.g., a( -> ă, a^ -> â, a' -> á)
+    Viqr,
+    /// No marks typed at all: the accented words and phrases the letters
+    /// could be are offered as candidates (e.g., viet nam -> Việt Nam)
+    Accentless,
 }
 
-impl Default for InputMethod {
-    fn default() -> Self {
-        Self::Telex
+impl InputMethod {
+    /// Every method, in the order the CLI cycles through them
+    pub const ALL: [InputMethod; 6] = [
+        InputMethod::Telex,
+        InputMethod::SimpleTelex,
+        InputMethod::Telex2,
+        InputMethod::VNI,
+        InputMethod::Viqr,
+        InputMethod::Accentless,
+    ];
+
+    /// Identifier used in configuration and the C API (`"simple-telex"`)
+    pub fn id(self) -> &'static str {
+        match self {
+            InputMethod::Telex => "telex",
+            InputMethod::SimpleTelex => "simple-telex",
+            InputMethod::Telex2 => "telex2",
+            InputMethod::VNI => "vni",
+            InputMethod::Viqr => "viqr",
+            InputMethod::Accentless => "accentless",
+        }
+    }
+
+    /// Look up a method by its [`id`](Self::id)
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|method| method.id() == id)
+    }
+
+    /// Display name (`"Simple Telex"`)
+    pub fn name(self) -> &'static str {
+        match self {
+            InputMethod::Telex => "Telex",
+            InputMethod::SimpleTelex => "Simple Telex",
+            InputMethod::Telex2 => "Telex 2",
+            InputMethod::VNI => "VNI",
+            InputMethod::Viqr => "VIQR",
+            InputMethod::Accentless => "Accent-less",
+        }
+    }
+
+    /// Whether this is one of the Telex variants
+    pub fn is_telex(self) -> bool {
+        matches!(self, InputMethod::Telex | InputMethod::SimpleTelex | InputMethod::Telex2)
     }
 }
 
 /// Tone mark in Vietnamese
+///
+/// Placed on one vowel of a syllable; see [`Syllable::tone_position`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ToneMark {
+pub enum ToneMark {
     None,       // no tone (a)
     Acute,      // sắc (á)
     Grave,      // huyền (à)
@@ -30,6 +94,22 @@ enum ToneMark {
     DotBelow,   // nặng (ạ)
 }
 
+impl ToneMark {
+    /// All tones, in the column order of `VOWELS`
+    const ALL: [ToneMark; 6] = [
+        ToneMark::None,
+        ToneMark::Acute,
+        ToneMark::Grave,
+        ToneMark::HookAbove,
+        ToneMark::Tilde,
+        ToneMark::DotBelow,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
 /// Vowel with modification (breve, circumflex, horn)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum VowelMod {
@@ -37,579 +117,902 @@ enum VowelMod {
     Breve,     // ă (from aw)
     Circumflex, // â, ê, ô (from aa, ee, oo)
     Horn,      // ơ, ư (from ow, uw)
+    Stroke,    // đ (from dd)
 }
 
+/// Every vowel form: base, modification, then one column per `ToneMark`
+const VOWELS: [(char, VowelMod, [char; 6]); 12] = [
+    ('a', VowelMod::None, ['a', 'á', 'à', 'ả', 'ã', 'ạ']),
+    ('a', VowelMod::Breve, ['ă', 'ắ', 'ằ', 'ẳ', 'ẵ', 'ặ']),
+    ('a', VowelMod::Circumflex, ['â', 'ấ', 'ầ', 'ẩ', 'ẫ', 'ậ']),
+    ('e', VowelMod::None, ['e', 'é', 'è', 'ẻ', 'ẽ', 'ẹ']),
+    ('e', VowelMod::Circumflex, ['ê', 'ế', 'ề', 'ể', 'ễ', 'ệ']),
+    ('i', VowelMod::None, ['i', 'í', 'ì', 'ỉ', 'ĩ', 'ị']),
+    ('o', VowelMod::None, ['o', 'ó', 'ò', 'ỏ', 'õ', 'ọ']),
+    ('o', VowelMod::Circumflex, ['ô', 'ố', 'ồ', 'ổ', 'ỗ', 'ộ']),
+    ('o', VowelMod::Horn, ['ơ', 'ớ', 'ờ', 'ở', 'ỡ', 'ợ']),
+    ('u', VowelMod::None, ['u', 'ú', 'ù', 'ủ', 'ũ', 'ụ']),
+    ('u', VowelMod::Horn, ['ư', 'ứ', 'ừ', 'ử', 'ữ', 'ự']),
+    ('y', VowelMod::None, ['y', 'ý', 'ỳ', 'ỷ', 'ỹ', 'ỵ']),
+];
+
 /// Character info for tone placement
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct CharInfo {
-    base: char,
+    base: char,  // always lowercase
     vowel_mod: VowelMod,
+    tone: ToneMark,
+    upper: bool,
     can_take_tone: bool,  // true for vowels, false for consonants
 }
 
 impl CharInfo {
     fn new(c: char) -> Self {
-        let (base, vowel_mod) = Self::parse_vowel(c);
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let (base, vowel_mod, tone) = Self::parse_vowel(lower);
         let can_take_tone = Self::is_vowel(base);
-        Self { base, vowel_mod, can_take_tone }
-    }
-
-    fn parse_vowel(c: char) -> (char, VowelMod) {
-        match c {
-            // Breve vowels
-            'ă' | 'Ă' | 'ắ' | 'Ắ' | 'ằ' | 'Ằ' | 'ẳ' | 'Ẳ' | 'ẵ' | 'Ẵ' | 'ặ' | 'Ặ' => ('a', VowelMod::Breve),
-            // Circumflex vowels
-            'â' | 'Â' | 'ấ' | 'Ấ' | 'ầ' | 'Ầ' | 'ẩ' | 'Ẩ' | 'ẫ' | 'Ẫ' | 'ậ' | 'Ậ' => ('a', VowelMod::Circumflex),
-            'ê' | 'Ê' | 'ế' | 'Ế' | 'ề' | 'Ề' | 'ể' | 'Ể' | 'ễ' | 'Ễ' | 'ệ' | 'Ệ' => ('e', VowelMod::Circumflex),
-            'ô' | 'Ô' | 'ố' | 'Ố' | 'ồ' | 'Ồ' | 'ổ' | 'Ổ' | 'ỗ' | 'Ỗ' | 'ộ' | 'Ộ' => ('o', VowelMod::Circumflex),
-            // Horn vowels
-            'ơ' | 'Ơ' | 'ớ' | 'Ớ' | 'ờ' | 'Ờ' | 'ở' | 'Ở' | 'ỡ' | 'Ỡ' | 'ợ' | 'Ợ' => ('o', VowelMod::Horn),
-            'ư' | 'Ư' | 'ứ' | 'Ứ' | 'ừ' | 'Ừ' | 'ử' | 'Ử' | 'ữ' | 'Ữ' | 'ự' | 'Ự' => ('u', VowelMod::Horn),
-            // đ
-            'đ' | 'Đ' => ('d', VowelMod::None),
-            // Tone marked base vowels - return base without modification
-            'á' | 'Á' | 'à' | 'À' | 'ả' | 'Ả' | 'ã' | 'Ã' | 'ạ' | 'Ạ' => ('a', VowelMod::None),
-            'é' | 'É' | 'è' | 'È' | 'ẻ' | 'Ẻ' | 'ẽ' | 'Ẽ' | 'ẹ' | 'Ẹ' => ('e', VowelMod::None),
-            'í' | 'Í' | 'ì' | 'Ì' | 'ỉ' | 'Ỉ' | 'ĩ' | 'Ĩ' | 'ị' | 'Ị' => ('i', VowelMod::None),
-            'ó' | 'Ó' | 'ò' | 'Ò' | 'ỏ' | 'Ỏ' | 'õ' | 'Õ' | 'ọ' | 'Ọ' => ('o', VowelMod::None),
-            'ú' | 'Ú' | 'ù' | 'Ù' | 'ủ' | 'Ủ' | 'ũ' | 'Ũ' | 'ụ' | 'Ụ' => ('u', VowelMod::None),
-            'ý' | 'Ý' | 'ỳ' | 'Ỳ' | 'ỷ' | 'Ỷ' | 'ỹ' | 'Ỹ' | 'ỵ' | 'Ỵ' => ('y', VowelMod::None),
-            // Default: pass through
-            _ => (c, VowelMod::None),
+        Self { base, vowel_mod, tone, upper: lower != c, can_take_tone }
+    }
+
+    /// Parse every character of a composing buffer
+    ///
+    /// Combining diacritics join the letter before them, so decomposed
+    /// (NFD) text reads the same as precomposed.
+    fn parse(text: &str) -> Vec<CharInfo> {
+        let mut chars: Vec<CharInfo> = Vec::new();
+        for c in text.chars() {
+            if !chars.last_mut().is_some_and(|prev| normalization::combine(prev, c)) {
+                chars.push(CharInfo::new(c));
+            }
+        }
+        chars
+    }
+
+    /// Render parsed characters back into text
+    fn render(chars: &[CharInfo]) -> String {
+        chars.iter().map(|ch| ch.with_tone(ch.tone)).collect()
+    }
+
+    /// Split a lowercase character into base, modification and tone
+    fn parse_vowel(c: char) -> (char, VowelMod, ToneMark) {
+        if c == 'đ' {
+            return ('d', VowelMod::Stroke, ToneMark::None);
         }
+        for (base, vowel_mod, forms) in VOWELS {
+            if let Some(i) = forms.iter().position(|&form| form == c) {
+                return (base, vowel_mod, ToneMark::ALL[i]);
+            }
+        }
+        // Default: pass through
+        (c, VowelMod::None, ToneMark::None)
     }
 
     fn is_vowel(c: char) -> bool {
         matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
     }
 
-    /// Find the best position for tone mark in a sequence of chars
-    fn find_tone_position(chars: &[CharInfo]) -> Option<usize> {
-        // Priority: ă > â > ê > ô > ơ > ư > a > e > i > o > u > y
-        // Look for modified vowels first, then base vowels
-        for (i, ch) in chars.iter().enumerate() {
-            if !ch.can_take_tone {
-                continue;
+    /// Find where the tone mark goes: on the nucleus of the last syllable
+    fn find_tone_position(chars: &[CharInfo], style: ToneStyle) -> Option<usize> {
+        Syllable::last_of(chars).map(|(start, syllable)| start + syllable.tone_position(style))
+    }
+
+    /// Apply tone to this character, keeping its case
+    fn with_tone(&self, tone: ToneMark) -> char {
+        let lower = if self.vowel_mod == VowelMod::Stroke {
+            'đ'
+        } else {
+            VOWELS
+                .iter()
+                .find(|(base, vowel_mod, _)| *base == self.base && *vowel_mod == self.vowel_mod)
+                .map_or(self.base, |(_, _, forms)| forms[tone.index()])
+        };
+        if self.upper {
+            lower.to_uppercase().next().unwrap_or(lower)
+        } else {
+            lower
+        }
+    }
+
+    /// Where the syllable being typed starts, within the trailing word
+    fn syllable_start(chars: &[CharInfo]) -> Option<usize> {
+        let word = chars.iter().rposition(|ch| !ch.base.is_alphabetic()).map_or(0, |i| i + 1);
+        Syllable::start_of_last(&chars[word..]).map(|start| word + start)
+    }
+
+    /// Find the character a modifier key applies to, and its modification
+    ///
+    /// Any letter of the syllable being typed can take it, so the key may
+    /// come after the whole word: `tieng` + `e` gives `tiêng`, `di` + `d`
+    /// gives `đi`. The one nearest the end that leaves a syllable wins
+    /// (`mua` + `w` is `mưa`, not `muă`); failing that, a mark the key
+    /// already made is undone, and otherwise the last character or vowel
+    /// takes it.
+    fn modifier_target(chars: &[CharInfo], rule: impl Fn(char) -> Option<VowelMod>) -> Option<(usize, VowelMod)> {
+        let last = chars.len().checked_sub(1)?;
+        let start = Self::syllable_start(chars).unwrap_or(last);
+        let targets: Vec<(usize, VowelMod)> =
+            (start..=last).rev().filter_map(|i| rule(chars[i].base).map(|vowel_mod| (i, vowel_mod))).collect();
+
+        let fits = |&(pos, vowel_mod): &(usize, VowelMod)| {
+            let mut syllable = chars[start..].to_vec();
+            let ch = &mut syllable[pos - start];
+            ch.vowel_mod != vowel_mod && {
+                ch.vowel_mod = vowel_mod;
+                is_vietnamese_prefix(&syllable)
             }
-            match ch.vowel_mod {
-                VowelMod::Breve => return Some(i),     // ă - highest priority
-                VowelMod::Circumflex => return Some(i), // â, ê, ô
-                VowelMod::Horn => return Some(i),      // ơ, ư
-                VowelMod::None => {}
+        };
+        let last_vowel = chars.iter().rposition(|ch| ch.can_take_tone);
+        targets
+            .iter()
+            .find(|target| fits(target))
+            .or_else(|| targets.iter().find(|&&(pos, vowel_mod)| chars[pos].vowel_mod == vowel_mod))
+            .or_else(|| targets.iter().find(|&&(pos, _)| pos == last || Some(pos) == last_vowel))
+            .copied()
+    }
+
+    /// Apply `vowel_mod` to the character at `pos`, or undo it if already applied
+    ///
+    /// Undoing restores the plain letter and appends the modifier `key`
+    /// literally, so `aww` gives `aw`; `ươ` loses both horns at once.
+    fn modify(chars: &mut Vec<CharInfo>, pos: usize, vowel_mod: VowelMod, key: char) {
+        if chars[pos].vowel_mod == vowel_mod {
+            chars[pos].vowel_mod = VowelMod::None;
+            if vowel_mod == VowelMod::Horn && chars[pos].base == 'o' && pos > 0 {
+                let prev = &mut chars[pos - 1];
+                if prev.base == 'u' && prev.vowel_mod == VowelMod::Horn {
+                    prev.vowel_mod = VowelMod::None;
+                }
             }
+            chars.push(CharInfo::new(key));
+        } else {
+            chars[pos].vowel_mod = vowel_mod;
         }
+    }
 
-        // No modified vowels, find first regular vowel
-        for (i, ch) in chars.iter().enumerate() {
-            if ch.can_take_tone && ch.vowel_mod == VowelMod::None {
-                return Some(i);
-            }
+    /// Give `ươ` both horns once the syllable being typed has a final
+    fn complete_horns(chars: &mut [CharInfo]) {
+        let Some(start) = Self::syllable_start(chars) else {
+            return;
+        };
+        if let Some(mut syllable) = Syllable::from_chars(chars[start..].to_vec()) {
+            syllable.complete_horns();
+            chars[start..].clone_from_slice(&syllable.into_chars());
         }
+    }
 
-        None
+    /// Bring the last syllable in line with its current shape
+    ///
+    /// Typing on after a tone or modifier key changes the nucleus: `hòa` +
+    /// `n` gives `hoàn`, `tién` + `e` gives `tiến`, `truơ` + `n` gives `trươn`.
+    fn reshape(chars: &mut Vec<CharInfo>, style: ToneStyle) {
+        Self::complete_horns(chars);
+        if let Some((start, mut syllable)) = Syllable::last_of(chars) {
+            syllable.set_tone(syllable.tone(), style);
+            chars.truncate(start);
+            chars.extend(syllable.into_chars());
+        }
     }
 
-    /// Apply tone to this character
-    fn with_tone(&self, tone: ToneMark) -> char {
-        let is_upper = self.base.is_ascii_uppercase();
-
-        let result = match (self.base, self.vowel_mod, tone) {
-            // Special vowels with modifications (base chars that already have modification)
-            ('ă', VowelMod::Breve, ToneMark::Acute) => 'ắ',
-            ('ă', VowelMod::Breve, ToneMark::Grave) => 'ằ',
-            ('ă', VowelMod::Breve, ToneMark::HookAbove) => 'ẳ',
-            ('ă', VowelMod::Breve, ToneMark::Tilde) => 'ẵ',
-            ('ă', VowelMod::Breve, ToneMark::DotBelow) => 'ặ',
-            ('ă', VowelMod::Breve, ToneMark::None) => 'ă',
-
-            ('â', VowelMod::Circumflex, ToneMark::Acute) => 'ấ',
-            ('â', VowelMod::Circumflex, ToneMark::Grave) => 'ầ',
-            ('â', VowelMod::Circumflex, ToneMark::HookAbove) => 'ẩ',
-            ('â', VowelMod::Circumflex, ToneMark::Tilde) => 'ẫ',
-            ('â', VowelMod::Circumflex, ToneMark::DotBelow) => 'ậ',
-            ('â', VowelMod::Circumflex, ToneMark::None) => 'â',
-
-            ('ê', VowelMod::Circumflex, ToneMark::Acute) => 'ế',
-            ('ê', VowelMod::Circumflex, ToneMark::Grave) => 'ề',
-            ('ê', VowelMod::Circumflex, ToneMark::HookAbove) => 'ể',
-            ('ê', VowelMod::Circumflex, ToneMark::Tilde) => 'ễ',
-            ('ê', VowelMod::Circumflex, ToneMark::DotBelow) => 'ệ',
-            ('ê', VowelMod::Circumflex, ToneMark::None) => 'ê',
-
-            ('ô', VowelMod::Circumflex, ToneMark::Acute) => 'ố',
-            ('ô', VowelMod::Circumflex, ToneMark::Grave) => 'ồ',
-            ('ô', VowelMod::Circumflex, ToneMark::HookAbove) => 'ổ',
-            ('ô', VowelMod::Circumflex, ToneMark::Tilde) => 'ỗ',
-            ('ô', VowelMod::Circumflex, ToneMark::DotBelow) => 'ộ',
-            ('ô', VowelMod::Circumflex, ToneMark::None) => 'ô',
-
-            ('ơ', VowelMod::Horn, ToneMark::Acute) => 'ớ',
-            ('ơ', VowelMod::Horn, ToneMark::Grave) => 'ờ',
-            ('ơ', VowelMod::Horn, ToneMark::HookAbove) => 'ở',
-            ('ơ', VowelMod::Horn, ToneMark::Tilde) => 'ỡ',
-            ('ơ', VowelMod::Horn, ToneMark::DotBelow) => 'ợ',
-            ('ơ', VowelMod::Horn, ToneMark::None) => 'ơ',
-
-            ('ư', VowelMod::Horn, ToneMark::Acute) => 'ứ',
-            ('ư', VowelMod::Horn, ToneMark::Grave) => 'ừ',
-            ('ư', VowelMod::Horn, ToneMark::HookAbove) => 'ử',
-            ('ư', VowelMod::Horn, ToneMark::Tilde) => 'ữ',
-            ('ư', VowelMod::Horn, ToneMark::DotBelow) => 'ự',
-            ('ư', VowelMod::Horn, ToneMark::None) => 'ư',
-
-            // Base vowels (ASCII) with modifications - when parsed then tone applied
-            ('a', VowelMod::Breve, ToneMark::Acute) => 'ắ',
-            ('a', VowelMod::Breve, ToneMark::Grave) => 'ằ',
-            ('a', VowelMod::Breve, ToneMark::HookAbove) => 'ẳ',
-            ('a', VowelMod::Breve, ToneMark::Tilde) => 'ẵ',
-            ('a', VowelMod::Breve, ToneMark::DotBelow) => 'ặ',
-            ('a', VowelMod::Breve, ToneMark::None) => 'ă',
-
-            ('a', VowelMod::Circumflex, ToneMark::Acute) => 'ấ',
-            ('a', VowelMod::Circumflex, ToneMark::Grave) => 'ầ',
-            ('a', VowelMod::Circumflex, ToneMark::HookAbove) => 'ẩ',
-            ('a', VowelMod::Circumflex, ToneMark::Tilde) => 'ẫ',
-            ('a', VowelMod::Circumflex, ToneMark::DotBelow) => 'ậ',
-            ('a', VowelMod::Circumflex, ToneMark::None) => 'â',
-
-            ('e', VowelMod::Circumflex, ToneMark::Acute) => 'ế',
-            ('e', VowelMod::Circumflex, ToneMark::Grave) => 'ề',
-            ('e', VowelMod::Circumflex, ToneMark::HookAbove) => 'ể',
-            ('e', VowelMod::Circumflex, ToneMark::Tilde) => 'ễ',
-            ('e', VowelMod::Circumflex, ToneMark::DotBelow) => 'ệ',
-            ('e', VowelMod::Circumflex, ToneMark::None) => 'ê',
-
-            ('o', VowelMod::Circumflex, ToneMark::Acute) => 'ố',
-            ('o', VowelMod::Circumflex, ToneMark::Grave) => 'ồ',
-            ('o', VowelMod::Circumflex, ToneMark::HookAbove) => 'ổ',
-            ('o', VowelMod::Circumflex, ToneMark::Tilde) => 'ỗ',
-            ('o', VowelMod::Circumflex, ToneMark::DotBelow) => 'ộ',
-            ('o', VowelMod::Circumflex, ToneMark::None) => 'ô',
-
-            ('o', VowelMod::Horn, ToneMark::Acute) => 'ớ',
-            ('o', VowelMod::Horn, ToneMark::Grave) => 'ờ',
-            ('o', VowelMod::Horn, ToneMark::HookAbove) => 'ở',
-            ('o', VowelMod::Horn, ToneMark::Tilde) => 'ỡ',
-            ('o', VowelMod::Horn, ToneMark::DotBelow) => 'ợ',
-            ('o', VowelMod::Horn, ToneMark::None) => 'ơ',
-
-            ('u', VowelMod::Horn, ToneMark::Acute) => 'ứ',
-            ('u', VowelMod::Horn, ToneMark::Grave) => 'ừ',
-            ('u', VowelMod::Horn, ToneMark::HookAbove) => 'ử',
-            ('u', VowelMod::Horn, ToneMark::Tilde) => 'ữ',
-            ('u', VowelMod::Horn, ToneMark::DotBelow) => 'ự',
-            ('u', VowelMod::Horn, ToneMark::None) => 'ư',
-
-            // Base vowels (ASCII) with tones
-            ('a', VowelMod::None, ToneMark::Acute) => 'á',
-            ('a', VowelMod::None, ToneMark::Grave) => 'à',
-            ('a', VowelMod::None, ToneMark::HookAbove) => 'ả',
-            ('a', VowelMod::None, ToneMark::Tilde) => 'ã',
-            ('a', VowelMod::None, ToneMark::DotBelow) => 'ạ',
-            ('a', VowelMod::None, ToneMark::None) => 'a',
-
-            ('e', VowelMod::None, ToneMark::Acute) => 'é',
-            ('e', VowelMod::None, ToneMark::Grave) => 'è',
-            ('e', VowelMod::None, ToneMark::HookAbove) => 'ẻ',
-            ('e', VowelMod::None, ToneMark::Tilde) => 'ẽ',
-            ('e', VowelMod::None, ToneMark::DotBelow) => 'ẹ',
-            ('e', VowelMod::None, ToneMark::None) => 'e',
-
-            ('i', VowelMod::None, ToneMark::Acute) => 'í',
-            ('i', VowelMod::None, ToneMark::Grave) => 'ì',
-            ('i', VowelMod::None, ToneMark::HookAbove) => 'ỉ',
-            ('i', VowelMod::None, ToneMark::Tilde) => 'ĩ',
-            ('i', VowelMod::None, ToneMark::DotBelow) => 'ị',
-            ('i', VowelMod::None, ToneMark::None) => 'i',
-
-            ('o', VowelMod::None, ToneMark::Acute) => 'ó',
-            ('o', VowelMod::None, ToneMark::Grave) => 'ò',
-            ('o', VowelMod::None, ToneMark::HookAbove) => 'ỏ',
-            ('o', VowelMod::None, ToneMark::Tilde) => 'õ',
-            ('o', VowelMod::None, ToneMark::DotBelow) => 'ọ',
-            ('o', VowelMod::None, ToneMark::None) => 'o',
-
-            ('u', VowelMod::None, ToneMark::Acute) => 'ú',
-            ('u', VowelMod::None, ToneMark::Grave) => 'ù',
-            ('u', VowelMod::None, ToneMark::HookAbove) => 'ủ',
-            ('u', VowelMod::None, ToneMark::Tilde) => 'ũ',
-            ('u', VowelMod::None, ToneMark::DotBelow) => 'ụ',
-            ('u', VowelMod::None, ToneMark::None) => 'u',
-
-            ('y', VowelMod::None, ToneMark::Acute) => 'ý',
-            ('y', VowelMod::None, ToneMark::Grave) => 'ỳ',
-            ('y', VowelMod::None, ToneMark::HookAbove) => 'ỷ',
-            ('y', VowelMod::None, ToneMark::Tilde) => 'ỹ',
-            ('y', VowelMod::None, ToneMark::DotBelow) => 'ỵ',
-            ('y', VowelMod::None, ToneMark::None) => 'y',
-
-            // Special consonants
-            ('d', VowelMod::None, ToneMark::None) => 'đ',
-
-            // Consonants and other chars pass through
-            (base, _, _) => base,
+    /// Place `tone` on the syllable, or undo it if already placed
+    ///
+    /// Undoing removes the tone and appends the tone `key` literally, so
+    /// `ass` gives `as`; removing a tone that isn't there keeps the key too.
+    /// Returns `false` if there is no vowel to take a tone.
+    fn retone(chars: &mut Vec<CharInfo>, tone: ToneMark, key: char, style: ToneStyle) -> bool {
+        let Some((start, mut syllable)) = Syllable::last_of(chars) else {
+            return false;
         };
+        let current = syllable.tone();
+        syllable.set_tone(if tone == current { ToneMark::None } else { tone }, style);
+        chars.truncate(start);
+        chars.extend(syllable.into_chars());
+        if tone == current {
+            chars.push(CharInfo::new(key));
+        }
+        true
+    }
+}
 
-        if is_upper {
-            // For now, just return the lowercase result
-            // TODO: Implement proper uppercase conversion
-            result
-        } else {
-            result
+/// Vowel modification a Telex key applies after `base`
+fn telex_vowel_mod(base: char, key: char) -> Option<VowelMod> {
+    match (base, key.to_ascii_lowercase()) {
+        ('a', 'w') => Some(VowelMod::Breve),
+        ('a', 'a') | ('e', 'e') | ('o', 'o') => Some(VowelMod::Circumflex),
+        ('o', 'w') | ('u', 'w') => Some(VowelMod::Horn),
+        ('d', 'd') => Some(VowelMod::Stroke),
+        _ => None,
+    }
+}
+
+/// Whether `chars` form a syllable, or one still being typed
+fn is_vietnamese_prefix(chars: &[CharInfo]) -> bool {
+    Syllable::from_chars(chars.to_vec()).is_some_and(|mut syllable| {
+        syllable.complete_horns();
+        syllable.is_valid_prefix()
+    })
+}
+
+/// Whether `chars` form a syllable being typed, or could once vowel
+/// modifiers are added to it (`tién` on its way to `tiến`)
+fn may_become_vietnamese(chars: &[CharInfo]) -> bool {
+    is_vietnamese_prefix(chars) || modifiable_from(&mut chars.to_vec(), 0)
+}
+
+/// Whether some modifiers on the plain vowels of `chars` from `start` on
+/// make them a syllable being typed
+fn modifiable_from(chars: &mut [CharInfo], start: usize) -> bool {
+    let plain = chars[start..].iter().position(|ch| ch.can_take_tone && ch.vowel_mod == VowelMod::None);
+    let Some(pos) = plain.map(|offset| start + offset) else {
+        return is_vietnamese_prefix(chars);
+    };
+    let mods: &[VowelMod] = match chars[pos].base {
+        'a' => &[VowelMod::None, VowelMod::Breve, VowelMod::Circumflex],
+        'e' => &[VowelMod::None, VowelMod::Circumflex],
+        'o' => &[VowelMod::None, VowelMod::Circumflex, VowelMod::Horn],
+        'u' => &[VowelMod::None, VowelMod::Horn],
+        _ => &[VowelMod::None],
+    };
+    let found = mods.iter().any(|&vowel_mod| {
+        chars[pos].vowel_mod = vowel_mod;
+        modifiable_from(chars, pos + 1)
+    });
+    chars[pos].vowel_mod = VowelMod::None;
+    found
+}
+
+/// Initial a doubled Telex consonant stands for (`cc` → `ch`)
+fn quick_initial(key: char) -> Option<&'static str> {
+    match key.to_ascii_lowercase() {
+        'c' => Some("ch"),
+        'g' => Some("gi"),
+        'k' => Some("kh"),
+        'n' => Some("ng"),
+        'p' => Some("ph"),
+        't' => Some("th"),
+        'q' => Some("qu"),
+        _ => None,
+    }
+}
+
+/// Final consonant a Telex key stands for right after a vowel (`g` → `ng`)
+fn quick_ending(key: char) -> Option<&'static str> {
+    match key.to_ascii_lowercase() {
+        'g' => Some("ng"),
+        'h' => Some("nh"),
+        'k' => Some("ch"),
+        _ => None,
+    }
+}
+
+/// Letter a standalone Telex shortcut key types (`w` → `ư`, `[` → `ơ`)
+fn telex_shortcut(key: char) -> Option<char> {
+    match key {
+        'w' | ']' => Some('ư'),
+        'W' | '}' => Some('Ư'),
+        '[' => Some('ơ'),
+        '{' => Some('Ơ'),
+        _ => None,
+    }
+}
+
+/// `c` in the case of `like`
+fn cased(c: char, like: char) -> char {
+    if like.is_uppercase() {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// `text` in the case `typed` was typed in: capitalized or all capitals
+fn cased_like(text: &str, typed: &str) -> String {
+    let mut letters = typed.chars().filter(|c| c.is_alphabetic());
+    match (letters.next(), letters.next()) {
+        (Some(first), Some(_)) if first.is_uppercase() && typed.chars().all(|c| !c.is_lowercase()) => {
+            text.to_uppercase()
+        }
+        (Some(first), _) if first.is_uppercase() => {
+            let mut chars = text.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
         }
+        _ => text.to_string(),
     }
 }
 
+/// Candidate for dictionary text, a phrase if it has several words
+fn dictionary_candidate(text: String, confidence: f64) -> Candidate {
+    let kind = if text.contains(' ') { CandidateKind::Phrase } else { CandidateKind::Word };
+    Candidate::new(text).with_kind(kind).with_confidence(confidence as f32)
+}
+
+/// Tone a Telex key applies
+fn telex_tone(key: char) -> Option<ToneMark> {
+    match key.to_ascii_lowercase() {
+        's' => Some(ToneMark::Acute),      // sắc
+        'f' => Some(ToneMark::Grave),      // huyền
+        'r' => Some(ToneMark::HookAbove),  // hỏi
+        'x' => Some(ToneMark::Tilde),      // ngã
+        'j' => Some(ToneMark::DotBelow),   // nặng
+        'z' => Some(ToneMark::None),       // remove tone
+        _ => None,
+    }
+}
+
+/// Vowel modification a VNI key applies after `base`
+fn vni_vowel_mod(base: char, key: char) -> Option<VowelMod> {
+    match (base, key) {
+        ('a', '8') => Some(VowelMod::Breve),
+        ('a', '6') | ('e', '6') | ('o', '6') => Some(VowelMod::Circumflex),
+        ('o', '7') | ('u', '7') => Some(VowelMod::Horn),
+        ('d', '9') => Some(VowelMod::Stroke),
+        _ => None,
+    }
+}
+
+/// Tone a VNI key applies
+fn vni_tone(key: char) -> Option<ToneMark> {
+    match key {
+        '1' => Some(ToneMark::Acute),      // sắc
+        '2' => Some(ToneMark::Grave),      // huyền
+        '3' => Some(ToneMark::HookAbove),  // hỏi
+        '4' => Some(ToneMark::Tilde),      // ngã
+        '5' => Some(ToneMark::DotBelow),   // nặng
+        _ => None,
+    }
+}
+
+/// Vowel modification a VIQR mark applies to `base` right before it
+fn viqr_vowel_mod(base: char, key: char) -> Option<VowelMod> {
+    match (base, key.to_ascii_lowercase()) {
+        ('a', '(') => Some(VowelMod::Breve),
+        ('a', '^') | ('e', '^') | ('o', '^') => Some(VowelMod::Circumflex),
+        ('o', '+') | ('u', '+') => Some(VowelMod::Horn),
+        ('d', '-') | ('d', 'd') => Some(VowelMod::Stroke),
+        _ => None,
+    }
+}
+
+/// Tone a VIQR mark applies to the vowel right before it
+fn viqr_tone(key: char) -> Option<ToneMark> {
+    match key {
+        '\'' => Some(ToneMark::Acute),     // sắc
+        '`' => Some(ToneMark::Grave),      // huyền
+        '?' => Some(ToneMark::HookAbove),  // hỏi
+        '~' => Some(ToneMark::Tilde),      // ngã
+        '.' => Some(ToneMark::DotBelow),   // nặng
+        _ => None,
+    }
+}
+
+/// Most candidates `generate_candidates` returns
+const MAX_CANDIDATES: usize = 20;
+
 /// Vietnamese language pack
 pub struct Vietnamese {
     method: InputMethod,
+    tone_style: ToneStyle,
+    spell_check: bool,
+    auto_restore: bool,
+    quick_consonants: bool,
+    quick_endings: bool,
+    telex_shortcuts: bool,
+    normalization: Normalization,
 }
 
 impl Vietnamese {
     pub fn new() -> Self {
+        Self::with_method(InputMethod::default())
+    }
+
+    pub fn with_method(method: InputMethod) -> Self {
         Self {
-            method: InputMethod::default(),
+            method,
+            tone_style: ToneStyle::default(),
+            spell_check: false,
+            auto_restore: false,
+            quick_consonants: false,
+            quick_endings: false,
+            telex_shortcuts: false,
+            normalization: Normalization::default(),
         }
     }
 
-    pub fn with_method(method: InputMethod) -> Self {
-        Self { method }
+    /// Place tones on `oa`, `oe` and `uy` following `style`
+    pub fn with_tone_style(mut self, style: ToneStyle) -> Self {
+        self.tone_style = style;
+        self
+    }
+
+    /// Get the tone placement style
+    pub fn tone_style(&self) -> ToneStyle {
+        self.tone_style
+    }
+
+    /// Only apply marks that leave a Vietnamese syllable (off by default)
+    ///
+    /// With this on, `class`, `word` and `process` are kept as typed: a
+    /// tone or modifier key that would make the composition something no
+    /// Vietnamese syllable can be is taken as a plain letter instead.
+    pub fn with_spell_check(mut self, enabled: bool) -> Self {
+        self.spell_check = enabled;
+        self
+    }
+
+    /// Check whether the spelling gate is on
+    pub fn spell_check(&self) -> bool {
+        self.spell_check
+    }
+
+    /// Commit the keys as typed when the word isn't Vietnamese (off by default)
+    ///
+    /// On commit, a composition carrying marks that is not a valid syllable
+    /// is replaced by its raw keys: `text` gives `tẽt` while typing but
+    /// commits `text`. Compositions without marks are committed as shown.
+    pub fn with_auto_restore(mut self, enabled: bool) -> Self {
+        self.auto_restore = enabled;
+        self
+    }
+
+    /// Check whether invalid words are restored to their keys on commit
+    pub fn auto_restore(&self) -> bool {
+        self.auto_restore
+    }
+
+    /// Telex: type a doubled consonant for an initial (off by default)
+    ///
+    /// `cc` → `ch`, `gg` → `gi`, `kk` → `kh`, `nn` → `ng`, `pp` → `ph`,
+    /// `tt` → `th`, `qq` → `qu` at the start of a word; a third press
+    /// undoes it and keeps the key (`ccc` → `cc`).
+    pub fn with_quick_consonants(mut self, enabled: bool) -> Self {
+        self.quick_consonants = enabled;
+        self
+    }
+
+    /// Check whether quick consonants are on
+    pub fn quick_consonants(&self) -> bool {
+        self.quick_consonants
+    }
+
+    /// Telex: type one key for a final consonant (off by default)
+    ///
+    /// Right after a vowel, `g` → `ng`, `h` → `nh` and `k` → `ch`; pressing
+    /// the key again undoes it and keeps the key (`agg` → `ag`).
+    pub fn with_quick_endings(mut self, enabled: bool) -> Self {
+        self.quick_endings = enabled;
+        self
+    }
+
+    /// Check whether quick endings are on
+    pub fn quick_endings(&self) -> bool {
+        self.quick_endings
+    }
+
+    /// Telex: `w`, `[` and `]` type a letter on their own (off by default)
+    ///
+    /// `w` gives `ư` where it doesn't modify a vowel, `[` gives `ơ` and `]`
+    /// gives `ư` (`{` and `}` the capitals). The brackets then compose
+    /// instead of committing. Pressing the key again gives it back: `ww` → `w`.
+    ///
+    /// Only affects `InputMethod::Telex`; `Telex2` always has the shortcuts
+    /// and `SimpleTelex` never does.
+    pub fn with_telex_shortcuts(mut self, enabled: bool) -> Self {
+        self.telex_shortcuts = enabled;
+        self
+    }
+
+    /// Check whether the standalone Telex shortcuts are on
+    pub fn telex_shortcuts(&self) -> bool {
+        self.telex_shortcuts
+    }
+
+    /// Emit composing and committed text in `form` (NFC by default)
+    ///
+    /// Input in either form is accepted.
+    pub fn with_normalization(mut self, form: Normalization) -> Self {
+        self.normalization = form;
+        self
+    }
+
+    /// Get the normalization form of emitted text
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Letter a standalone shortcut key types, if the method has them
+    fn shortcut(&self, key: char) -> Option<char> {
+        let enabled = match self.method {
+            InputMethod::Telex => self.telex_shortcuts,
+            InputMethod::Telex2 => true,
+            InputMethod::SimpleTelex | InputMethod::VNI | InputMethod::Viqr | InputMethod::Accentless => false,
+        };
+        telex_shortcut(key).filter(|_| enabled)
+    }
+
+    /// Give back the key when a standalone shortcut is pressed twice
+    ///
+    /// Only the raw keys tell a standalone `ư` (`tw`) from a modified one
+    /// (`tuw`), whose second `w` undoes the horn instead.
+    fn undo_telex_shortcut(&self, keystroke: &Keystroke, buffer: &str, raw: &str) -> Option<String> {
+        let key = keystroke.typed_char()?;
+        let letter = self.shortcut(key)?;
+        let mut typed = raw.chars().rev();
+        let standalone = typed.next() == Some(key)
+            && !(key.eq_ignore_ascii_case(&'w') && typed.next().is_some_and(|c| c.eq_ignore_ascii_case(&'u')));
+        let rest = buffer.strip_suffix(letter).filter(|_| standalone)?;
+        Some(format!("{}{}", rest, key))
+    }
+
+    /// Apply or undo a quick consonant or ending shortcut for `key`
+    fn quick_telex(&self, chars: &[CharInfo], key: char) -> Option<Vec<CharInfo>> {
+        let lower = key.to_ascii_lowercase();
+
+        if let Some(initial) = quick_initial(key).filter(|_| self.quick_consonants) {
+            let spelled = syllable::spell(chars);
+            let mut letters = initial.chars();
+            let (first, second) = (letters.next()?, letters.next()?);
+            if spelled.chars().eq([lower]) {
+                return Some(vec![chars[0].clone(), CharInfo::new(cased(second, key))]);
+            }
+            if spelled == initial && lower == first {
+                return Some(vec![chars[0].clone(), CharInfo::new(key)]);
+            }
+        }
+
+        if let Some(ending) = quick_ending(key).filter(|_| self.quick_endings) {
+            if chars.last().is_some_and(|ch| ch.can_take_tone) {
+                let mut expanded = chars.to_vec();
+                expanded.extend(ending.chars().map(|c| CharInfo::new(cased(c, key))));
+                return Some(expanded).filter(|expanded| !self.spell_check || is_vietnamese_prefix(expanded));
+            }
+            let len = chars.len();
+            if len >= 3 && chars[len - 3].can_take_tone && syllable::spell(&chars[len - 2..]) == ending {
+                let mut undone = chars[..len - 2].to_vec();
+                undone.push(CharInfo::new(key));
+                return Some(undone);
+            }
+        }
+
+        None
+    }
+
+    /// Whether committing `composing` should give back the raw keys instead
+    fn should_restore(&self, composing: &str) -> bool {
+        self.auto_restore
+            && !composing.is_ascii()
+            && !Syllable::parse(composing).is_some_and(|syllable| syllable.is_valid())
+    }
+
+    /// Whether the spelling gate lets `before` be transformed into `after`
+    ///
+    /// Undoing a mark keeps its key, so `after` grows; that always stands.
+    /// `after` need only be valid once modifier keys still to come are
+    /// typed: `tiens` gives `tién`, for `tiense` to give `tiến`.
+    fn allows(&self, before: &[CharInfo], after: &[CharInfo]) -> bool {
+        !self.spell_check || after.len() > before.len() || may_become_vietnamese(after)
+    }
+
+    /// Re-place the tone on `chars` and report the change from `buffer`
+    ///
+    /// When the only change is `key` appended, the engine appends it itself.
+    fn updated(&self, mut chars: Vec<CharInfo>, buffer: &str, key: char) -> ProcessResult {
+        CharInfo::reshape(&mut chars, self.tone_style);
+        let text = CharInfo::render(&chars);
+        if text.strip_prefix(buffer).is_some_and(|added| added.chars().eq([key])) {
+            ProcessResult::Consumed
+        } else {
+            ProcessResult::BufferUpdated(text)
+        }
     }
 
     /// Convert a Telex string to Vietnamese
     ///
-    /// Processes both vowel modifications and tone marks.
+    /// Processes both vowel modifications and tone marks. Repeating a
+    /// modifier or tone key undoes it and keeps the key (`ass` → `as`).
+    /// A tone key before any vowel is a plain consonant (`sa`, `ruwowuj` →
+    /// `rượu`), as when typing keystroke by keystroke.
     pub fn convert_telex(&self, input: &str) -> String {
-        let mut result = String::with_capacity(input.len());
-        let input_chars: Vec<char> = input.chars().collect();
-        let mut i = 0;
-
         // First pass: Process all characters, collect char info and pending tone
         let mut chars: Vec<CharInfo> = Vec::new();
         let mut pending_tone: Option<ToneMark> = None;
 
-        while i < input_chars.len() {
-            let c = input_chars[i];
-
-            // Check for vowel modification digraphs first
-            if i + 1 < input_chars.len() {
-                let next = input_chars[i + 1];
-                let vowel_mod = match (c, next) {
-                    ('a', 'w') => Some(('ă', VowelMod::Breve)),
-                    ('a', 'a') => Some(('â', VowelMod::Circumflex)),
-                    ('o', 'w') => Some(('ơ', VowelMod::Horn)),
-                    ('o', 'o') => Some(('ô', VowelMod::Circumflex)),
-                    ('u', 'w') => Some(('ư', VowelMod::Horn)),
-                    ('d', 'd') => Some(('đ', VowelMod::None)),
-                    ('e', 'e') => Some(('ê', VowelMod::Circumflex)),
-                    _ => None,
-                };
-
-                if let Some((ch, vm)) = vowel_mod {
-                    // Use CharInfo::new to properly set can_take_tone
-                    // Then override vowel_mod since we know the modification
-                    let mut info = CharInfo::new(ch);
-                    info.vowel_mod = vm;
-                    chars.push(info);
-                    i += 2;
-                    continue;
-                }
+        for c in input.chars() {
+            // Check for vowel modification of the previous character or vowel
+            if let Some((pos, vowel_mod)) = CharInfo::modifier_target(&chars, |base| telex_vowel_mod(base, c)) {
+                CharInfo::modify(&mut chars, pos, vowel_mod, c);
+                CharInfo::complete_horns(&mut chars);
+                continue;
             }
 
-            // Check for tone mark
-            if c == 'x' || c == 'z' {
-                // x/z removes tone if it comes after a vowel
-                // Check if the previous character (in chars) is a vowel
-                let prev_is_vowel = chars.last().map_or(false, |ch| ch.can_take_tone);
-                if prev_is_vowel {
-                    pending_tone = Some(ToneMark::None);  // Remove tone
-                    i += 1;
-                    continue;
-                }
-                // Fall through: treat as regular character
-            } else {
-                let tone = match c {
-                    's' => Some(ToneMark::Acute),      // sắc
-                    'f' => Some(ToneMark::Grave),      // huyền
-                    'j' => Some(ToneMark::HookAbove),  // hỏi
-                    'r' => Some(ToneMark::DotBelow),   // nặng
-                    _ => None,
-                };
-
-                if let Some(t) = tone {
+            // Check for tone mark, which needs a vowel before it
+            let has_vowel = chars.iter().any(|ch| ch.can_take_tone);
+            if let Some(t) = telex_tone(c).filter(|_| has_vowel) {
+                if pending_tone.unwrap_or(ToneMark::None) == t {
+                    // Same tone twice, or z without a tone: keep the key
+                    pending_tone = None;
+                    chars.push(CharInfo::new(c));
+                } else {
                     pending_tone = Some(t);
-                    i += 1;
-                    continue;
                 }
+                continue;
             }
 
             // Regular character
             chars.push(CharInfo::new(c));
-            i += 1;
+            CharInfo::complete_horns(&mut chars);
         }
 
         // Second pass: Apply tone marks
-        let tone_to_apply: Option<ToneMark> = pending_tone;
-
-        // Build result string
-        // First, find tone position once
-        let tone_pos = if tone_to_apply.is_some() {
-            CharInfo::find_tone_position(&chars)
-        } else {
-            None
-        };
-
-        for (i, ch) in chars.iter().enumerate() {
-            // Check if this is the tone position
-            let has_tone = tone_pos == Some(i);
-
-            let ch_with_tone = if has_tone {
-                let tone = tone_to_apply.unwrap();
-                ch.with_tone(tone)
-            } else {
-                ch.with_tone(ToneMark::None)
-            };
-
-            result.push(ch_with_tone);
+        if let Some(tone) = pending_tone {
+            if let Some(pos) = CharInfo::find_tone_position(&chars, self.tone_style) {
+                chars[pos].tone = tone;
+            }
         }
 
-        result
+        CharInfo::render(&chars)
     }
 
     /// Process Telex input keystroke by keystroke
     fn process_telex(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
-        if let Keystroke { key: Key::Char(c), .. } = keystroke {
-            // Check for terminating characters (commit)
-            if c.is_ascii_whitespace() || c.is_ascii_punctuation() {
-                // Commit current buffer
-                return ProcessResult::ReadyToCommit(buffer.to_string());
-            }
-
-            let buffer_chars: Vec<char> = buffer.chars().collect();
-            let last_char = buffer_chars.last().copied();
-
-            // Check for Telex vowel modification (last char + current)
-            if let Some(last) = last_char {
-                let vowel_mod = match (last, c) {
-                    ('a', 'w') => Some('ă'),
-                    ('a', 'a') => Some('â'),
-                    ('o', 'w') => Some('ơ'),
-                    ('o', 'o') => Some('ô'),
-                    ('u', 'w') => Some('ư'),
-                    ('d', 'd') => Some('đ'),
-                    ('e', 'e') => Some('ê'),
-                    _ => None,
-                };
-
-                if let Some(replaced) = vowel_mod {
-                    // Replace last char with modified vowel
-                    let new_buffer: String = buffer_chars[..buffer_chars.len()-1].iter().collect();
-                    return ProcessResult::BufferUpdated(format!("{}{}", new_buffer, replaced));
+        if let Some(c) = keystroke.typed_char() {
+            let shortcut = self.shortcut(c);
+
+            // Check for terminating characters (commit), unless a shortcut
+            if (c.is_ascii_whitespace() || c.is_ascii_punctuation()) && shortcut.is_none() {
+                if buffer.is_empty() {
+                    return ProcessResult::PassThrough;
                 }
+                // Commit current buffer along with the terminator
+                return ProcessResult::ReadyToCommit(format!("{}{}", buffer, c));
             }
 
-            // Check for tone mark (s, f, j, r, x)
-            let tone = match c {
-                's' => Some(ToneMark::Acute),      // sắc
-                'f' => Some(ToneMark::Grave),      // huyền
-                'j' => Some(ToneMark::HookAbove),  // hỏi
-                'r' => Some(ToneMark::DotBelow),   // nặng
-                'x' | 'z' => Some(ToneMark::None),   // remove tone
-                _ => None,
-            };
+            let mut chars = CharInfo::parse(buffer);
 
-            if let Some(tone_mark) = tone {
+            // Check for quick consonants and endings (cc → ch, g → ng)
+            if let Some(expanded) = self.quick_telex(&chars, c) {
+                return self.updated(expanded, buffer, c);
+            }
+
+            // Check for Telex vowel modification (last char or vowel + current)
+            if let Some((pos, vowel_mod)) = CharInfo::modifier_target(&chars, |base| telex_vowel_mod(base, c)) {
+                let mut modified = chars.clone();
+                CharInfo::modify(&mut modified, pos, vowel_mod, c);
+                if self.allows(&chars, &modified) {
+                    return self.updated(modified, buffer, c);
+                }
+                // Not Vietnamese with the mark - treat as regular character
+            }
+
+            // Check for tone mark (s, f, r, x, j, z)
+            if let Some(tone_mark) = telex_tone(c) {
                 // Find the vowel to apply tone to
-                // Priority: ă > â > ê > ô > ơ > ư > a > e > i > o > u > y
-                let mut chars: Vec<CharInfo> = buffer_chars.iter().map(|&ch| CharInfo::new(ch)).collect();
-
-                if let Some(tone_pos) = CharInfo::find_tone_position(&chars) {
-                    // Apply tone to the character at tone_pos
-                    let target = &chars[tone_pos];
-                    let with_tone = target.with_tone(tone_mark);
-
-                    // Rebuild buffer with toned character
-                    let mut new_buffer = String::new();
-                    for (i, ch) in chars.iter().enumerate() {
-                        if i == tone_pos {
-                            new_buffer.push(with_tone);
-                        } else {
-                            new_buffer.push(ch.base);
-                        }
-                    }
-                    return ProcessResult::BufferUpdated(new_buffer);
+                let mut toned = chars.clone();
+                if CharInfo::retone(&mut toned, tone_mark, c, self.tone_style) && self.allows(&chars, &toned) {
+                    return ProcessResult::BufferUpdated(CharInfo::render(&toned));
+                }
+                // No vowel found to apply tone, or not Vietnamese with it - treat as regular character
+            }
+
+            // Check for standalone shortcuts (w → ư, [ → ơ, ] → ư)
+            if let Some(letter) = shortcut {
+                let mut typed = chars.clone();
+                typed.push(CharInfo::new(letter));
+                if !self.spell_check || is_vietnamese_prefix(&typed) {
+                    return self.updated(typed, buffer, c);
                 }
-                // No vowel found to apply tone - treat as regular character
             }
 
             // No special handling - append the character
-            ProcessResult::Consumed
+            chars.push(CharInfo::new(c));
+            self.updated(chars, buffer, c)
+        } else if keystroke.is_terminator() && keystroke.key != Key::Escape {
+            // Space/Tab/Enter/arrows: commit the word as composed
+            ProcessResult::ReadyToCommit(buffer.to_string())
         } else {
             // Non-character keystroke (backspace, etc.)
             ProcessResult::PassThrough
         }
     }
 
-    /// Convert a Telex string to Vietnamese
+    /// Convert a VNI string to Vietnamese
     ///
     /// VNI rules:
     /// - Vowel mods: a8→ă, a6→â, o7→ơ, o6→ô, u7→ư, d9→đ, e6→ê
     /// - Tone marks (at end): 1→sắc, 2→huyền, 3→hỏi, 4→ngã, 5→nặng
+    /// - Repeating a key undoes it and keeps the digit (`a11` → `a1`)
     pub fn convert_vni(&self, input: &str) -> String {
-        let mut result = String::with_capacity(input.len());
-        let input_chars: Vec<char> = input.chars().collect();
-        let mut i = 0;
-
         // First pass: Process vowel modifications and collect chars
         let mut chars: Vec<CharInfo> = Vec::new();
         let mut pending_tone: Option<ToneMark> = None;
 
-        while i < input_chars.len() {
-            let c = input_chars[i];
-
+        for c in input.chars() {
             // Check for VNI vowel modification (vowel + number)
-            if i + 1 < input_chars.len() {
-                let next = input_chars[i + 1];
-                let vowel_mod = match (c, next) {
-                    ('a', '8') => Some(('ă', VowelMod::Breve)),
-                    ('a', '6') => Some(('â', VowelMod::Circumflex)),
-                    ('o', '7') => Some(('ơ', VowelMod::Horn)),
-                    ('o', '6') => Some(('ô', VowelMod::Circumflex)),
-                    ('u', '7') => Some(('ư', VowelMod::Horn)),
-                    ('d', '9') => Some(('đ', VowelMod::None)),
-                    ('e', '6') => Some(('ê', VowelMod::Circumflex)),
-                    _ => None,
-                };
-
-                if let Some((ch, vm)) = vowel_mod {
-                    let mut info = CharInfo::new(ch);
-                    info.vowel_mod = vm;
-                    chars.push(info);
-                    i += 2;
-                    continue;
-                }
+            if let Some((pos, vowel_mod)) = CharInfo::modifier_target(&chars, |base| vni_vowel_mod(base, c)) {
+                CharInfo::modify(&mut chars, pos, vowel_mod, c);
+                CharInfo::complete_horns(&mut chars);
+                continue;
             }
 
             // Check for VNI tone mark (1-5)
             // In VNI, tone marks always come at the end of syllable
-            let tone = match c {
-                '1' => Some(ToneMark::Acute),      // sắc
-                '2' => Some(ToneMark::Grave),      // huyền
-                '3' => Some(ToneMark::HookAbove),  // hỏi
-                '4' => Some(ToneMark::Tilde),      // ngã
-                '5' => Some(ToneMark::DotBelow),   // nặng
-                _ => None,
-            };
-
-            if let Some(t) = tone {
-                // In VNI, tone marks override any previous tone
-                pending_tone = Some(t);
-                i += 1;
+            if let Some(t) = vni_tone(c) {
+                if pending_tone == Some(t) {
+                    // Same tone twice: undo it and keep the digit
+                    pending_tone = None;
+                    chars.push(CharInfo::new(c));
+                } else {
+                    // In VNI, tone marks override any previous tone
+                    pending_tone = Some(t);
+                }
                 continue;
             }
 
             // Regular character
             chars.push(CharInfo::new(c));
-            i += 1;
+            CharInfo::complete_horns(&mut chars);
         }
 
         // Second pass: Apply tone marks
-        // VNI always applies tone to the first vowel in the sequence
-        let tone_to_apply: Option<ToneMark> = pending_tone;
-
-        let tone_pos = if tone_to_apply.is_some() {
-            CharInfo::find_tone_position(&chars)
-        } else {
-            None
-        };
-
-        for (i, ch) in chars.iter().enumerate() {
-            let has_tone = tone_pos == Some(i);
-
-            let ch_with_tone = if has_tone {
-                let tone = tone_to_apply.unwrap();
-                ch.with_tone(tone)
-            } else {
-                ch.with_tone(ToneMark::None)
-            };
-
-            result.push(ch_with_tone);
+        if let Some(tone) = pending_tone {
+            if let Some(pos) = CharInfo::find_tone_position(&chars, self.tone_style) {
+                chars[pos].tone = tone;
+            }
         }
 
-        result
+        CharInfo::render(&chars)
     }
 
     /// Process VNI input keystroke by keystroke
     fn process_vni(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
-        if let Keystroke { key: Key::Char(c), .. } = keystroke {
+        if let Some(c) = keystroke.typed_char() {
             // Check for terminating characters (commit)
             if c.is_ascii_whitespace() || c.is_ascii_punctuation() {
-                // Commit current buffer
-                return ProcessResult::ReadyToCommit(buffer.to_string());
+                if buffer.is_empty() {
+                    return ProcessResult::PassThrough;
+                }
+                // Commit current buffer along with the terminator
+                return ProcessResult::ReadyToCommit(format!("{}{}", buffer, c));
             }
 
-            let buffer_chars: Vec<char> = buffer.chars().collect();
+            let mut chars = CharInfo::parse(buffer);
 
             // Check for VNI tone mark (1-5)
-            let tone = match c {
-                '1' => Some(ToneMark::Acute),      // sắc
-                '2' => Some(ToneMark::Grave),      // huyền
-                '3' => Some(ToneMark::HookAbove),  // hỏi
-                '4' => Some(ToneMark::Tilde),      // ngã
-                '5' => Some(ToneMark::DotBelow),   // nặng
-                _ => None,
-            };
-
-            if let Some(tone_mark) = tone {
-                // Apply tone to first vowel
-                let chars: Vec<CharInfo> = buffer_chars.iter().map(|&ch| CharInfo::new(ch)).collect();
-
-                if let Some(tone_pos) = CharInfo::find_tone_position(&chars) {
-                    // Apply tone to the character at tone_pos
-                    let target = &chars[tone_pos];
-                    let with_tone = target.with_tone(tone_mark);
-
-                    // Rebuild buffer with toned character
-                    let mut new_buffer = String::new();
-                    for (i, ch) in chars.iter().enumerate() {
-                        if i == tone_pos {
-                            new_buffer.push(with_tone);
-                        } else {
-                            new_buffer.push(ch.base);
-                        }
-                    }
-                    return ProcessResult::BufferUpdated(new_buffer);
+            if let Some(tone_mark) = vni_tone(c) {
+                let mut toned = chars.clone();
+                if CharInfo::retone(&mut toned, tone_mark, c, self.tone_style) && self.allows(&chars, &toned) {
+                    return ProcessResult::BufferUpdated(CharInfo::render(&toned));
                 }
-                // No vowel found - treat as regular character
-            }
-
-            // Check for VNI vowel modification (last char + current)
-            let last_char = buffer_chars.last().copied();
-            if let Some(last) = last_char {
-                let vowel_mod = match (last, c) {
-                    ('a', '8') => Some('ă'),
-                    ('a', '6') => Some('â'),
-                    ('o', '7') => Some('ơ'),
-                    ('o', '6') => Some('ô'),
-                    ('u', '7') => Some('ư'),
-                    ('d', '9') => Some('đ'),
-                    ('e', '6') => Some('ê'),
-                    _ => None,
-                };
-
-                if let Some(replaced) = vowel_mod {
-                    // Replace last char with modified vowel
-                    let new_buffer: String = buffer_chars[..buffer_chars.len()-1].iter().collect();
-                    return ProcessResult::BufferUpdated(format!("{}{}", new_buffer, replaced));
+                // No vowel found, or not Vietnamese with the tone - treat as regular character
+            }
+
+            // Check for VNI vowel modification (last char or vowel + current)
+            if let Some((pos, vowel_mod)) = CharInfo::modifier_target(&chars, |base| vni_vowel_mod(base, c)) {
+                let mut modified = chars.clone();
+                CharInfo::modify(&mut modified, pos, vowel_mod, c);
+                if self.allows(&chars, &modified) {
+                    return self.updated(modified, buffer, c);
                 }
             }
 
             // No special handling - append the character
-            ProcessResult::Consumed
+            chars.push(CharInfo::new(c));
+            self.updated(chars, buffer, c)
+        } else if keystroke.is_terminator() && keystroke.key != Key::Escape {
+            // Space/Tab/Enter/arrows: commit the word as composed
+            ProcessResult::ReadyToCommit(buffer.to_string())
+        } else {
+            // Non-character keystroke (backspace, etc.)
+            ProcessResult::PassThrough
+        }
+    }
+
+    /// Process VIQR input keystroke by keystroke
+    ///
+    /// Marks apply to the letter right before them (`vie^.t` → `việt`);
+    /// elsewhere they are punctuation. A backslash keeps the next mark
+    /// literal (`ba\?` → `ba?`).
+    fn process_viqr(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
+        if let Some(c) = keystroke.typed_char() {
+            let mut chars = CharInfo::parse(buffer);
+            let is_mark = viqr_tone(c).is_some() || matches!(c, '(' | '^' | '+' | '-');
+
+            // Escaped mark: replace the backslash with the mark itself
+            if let Some(rest) = buffer.strip_suffix('\\').filter(|_| is_mark) {
+                return ProcessResult::BufferUpdated(format!("{}{}", rest, c));
+            }
+            if c == '\\' && !buffer.is_empty() {
+                return ProcessResult::Consumed;
+            }
+
+            // Check for VIQR vowel modification (last char + mark)
+            if let Some(vowel_mod) = chars.last().and_then(|last| viqr_vowel_mod(last.base, c)) {
+                let mut modified = chars.clone();
+                CharInfo::modify(&mut modified, chars.len() - 1, vowel_mod, c);
+                if self.allows(&chars, &modified) {
+                    return self.updated(modified, buffer, c);
+                }
+            }
+
+            // Check for VIQR tone mark right after a vowel
+            let after_vowel = chars.last().is_some_and(|last| last.can_take_tone);
+            if let Some(tone_mark) = viqr_tone(c).filter(|_| after_vowel) {
+                let mut toned = chars.clone();
+                if CharInfo::retone(&mut toned, tone_mark, c, self.tone_style) && self.allows(&chars, &toned) {
+                    return ProcessResult::BufferUpdated(CharInfo::render(&toned));
+                }
+            }
+
+            // Check for terminating characters (commit)
+            if c.is_ascii_whitespace() || c.is_ascii_punctuation() {
+                if buffer.is_empty() {
+                    return ProcessResult::PassThrough;
+                }
+                // Commit current buffer along with the terminator
+                return ProcessResult::ReadyToCommit(format!("{}{}", buffer, c));
+            }
+
+            // No special handling - append the character
+            chars.push(CharInfo::new(c));
+            self.updated(chars, buffer, c)
+        } else if keystroke.is_terminator() && keystroke.key != Key::Escape {
+            // Space/Tab/Enter/arrows: commit the word as composed
+            ProcessResult::ReadyToCommit(buffer.to_string())
+        } else {
+            // Non-character keystroke (backspace, etc.)
+            ProcessResult::PassThrough
+        }
+    }
+
+    /// Process accent-less input keystroke by keystroke
+    ///
+    /// Letters are kept as typed and each one offers the accented forms
+    /// as candidates. Space keeps composing so phrases can be typed
+    /// (`viet nam`); a second Space or punctuation commits the letters as
+    /// they are. So does a first Space when there is nothing to suggest
+    /// (`xyz`), and Enter when no candidates are shown: while they are, the
+    /// engine takes Enter to select the highlighted one, and Escape gives
+    /// back the letters.
+    fn process_accentless(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
+        let space = matches!(keystroke.key, Key::Space | Key::Char(' '));
+        if space && !buffer.is_empty() && !buffer.ends_with(' ') {
+            let candidates = self.accentless_candidates(&format!("{} ", buffer));
+            if !candidates.is_empty() {
+                return ProcessResult::Candidates(candidates);
+            }
+        }
+        if let Some(c) = keystroke.typed_char() {
+            // Check for terminating characters (commit)
+            if c.is_ascii_whitespace() || c.is_ascii_punctuation() {
+                if buffer.is_empty() {
+                    return ProcessResult::PassThrough;
+                }
+                return ProcessResult::ReadyToCommit(format!("{}{}", buffer.trim_end(), c));
+            }
+            ProcessResult::Candidates(self.accentless_candidates(&format!("{}{}", buffer, c)))
+        } else if keystroke.is_terminator() && keystroke.key != Key::Escape {
+            // Tab/Enter/arrows: commit the letters as typed
+            ProcessResult::ReadyToCommit(buffer.trim_end().to_string())
         } else {
             // Non-character keystroke (backspace, etc.)
             ProcessResult::PassThrough
         }
     }
+
+    /// Accented readings of accent-less `typed`, as candidates
+    ///
+    /// Best first, with each one's share of the readings' scores as its
+    /// confidence; see [`Dictionary::readings`].
+    fn accentless_candidates(&self, typed: &str) -> CandidateList {
+        let readings = Dictionary::bundled().readings(typed);
+        let total: f64 = readings.iter().map(|(_, score)| score).sum();
+        readings
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|(text, score)| {
+                let text = normalize(&cased_like(&text, typed), self.normalization);
+                dictionary_candidate(text, if total > 0.0 { score / total } else { 0.0 })
+            })
+            .collect()
+    }
 }
 
 impl Default for Vietnamese {
@@ -618,22 +1021,76 @@ impl Default for Vietnamese {
     }
 }
 
-impl LanguagePack for Vietnamese {
-    fn process(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
+impl Vietnamese {
+    /// Process a keystroke for the input method, on an NFC buffer
+    fn process_composed(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
         match self.method {
-            InputMethod::Telex => self.process_telex(keystroke, buffer),
+            InputMethod::Telex | InputMethod::SimpleTelex | InputMethod::Telex2 => {
+                self.process_telex(keystroke, buffer)
+            }
             InputMethod::VNI => self.process_vni(keystroke, buffer),
+            InputMethod::Viqr => self.process_viqr(keystroke, buffer),
+            InputMethod::Accentless => self.process_accentless(keystroke, buffer),
         }
     }
 
-    fn generate_candidates(&self, _buffer: &str) -> CandidateList {
-        // TODO: Implement dictionary-based candidates (issue #4)
-        vec![]
+    /// Give the text in `result` the configured normalization form
+    fn normalized(&self, result: ProcessResult) -> ProcessResult {
+        match result {
+            ProcessResult::BufferUpdated(text) => ProcessResult::BufferUpdated(normalize(&text, self.normalization)),
+            ProcessResult::ReadyToCommit(text) => ProcessResult::ReadyToCommit(normalize(&text, self.normalization)),
+            other => other,
+        }
+    }
+}
+
+impl LanguagePack for Vietnamese {
+    fn process(&self, keystroke: &Keystroke, buffer: &str) -> ProcessResult {
+        let buffer = normalize(buffer, Normalization::Nfc);
+        self.normalized(self.process_composed(keystroke, &buffer))
+    }
+
+    fn process_with_raw(&self, keystroke: &Keystroke, buffer: &str, raw: &str) -> ProcessResult {
+        let buffer = normalize(buffer, Normalization::Nfc);
+        if let Some(text) = self.undo_telex_shortcut(keystroke, &buffer, raw) {
+            return self.normalized(ProcessResult::BufferUpdated(text));
+        }
+        let result = match self.process_composed(keystroke, &buffer) {
+            ProcessResult::ReadyToCommit(text) if self.should_restore(&buffer) => {
+                // Keep the terminator committed along with the word
+                let terminator = text.strip_prefix(buffer.as_str()).unwrap_or_default();
+                ProcessResult::ReadyToCommit(format!("{}{}", raw, terminator))
+            }
+            other => other,
+        };
+        self.normalized(result)
+    }
+
+    /// Dictionary words the buffer could be the beginning of
+    ///
+    /// Most frequent first, with each one's share of the matches'
+    /// frequency as its confidence: `vie` gives `việt`, `viên`, `viện`...
+    /// The accent-less method reads phrases too, as it offers them while
+    /// typing.
+    fn generate_candidates(&self, buffer: &str) -> CandidateList {
+        if self.method == InputMethod::Accentless {
+            return self.accentless_candidates(buffer);
+        }
+        let completions = Dictionary::bundled().completions(buffer);
+        let total: u64 = completions.iter().map(|entry| u64::from(entry.frequency())).sum();
+        completions
+            .into_iter()
+            .take(MAX_CANDIDATES)
+            .map(|entry| {
+                let text = normalize(&cased_like(entry.text(), buffer), self.normalization);
+                dictionary_candidate(text, f64::from(entry.frequency()) / total as f64)
+            })
+            .collect()
     }
 
     fn is_valid_composition(&self, buffer: &str) -> bool {
-        // Valid if contains printable Vietnamese-friendly characters
-        buffer.chars().all(|c| {
+        // Valid if contains printable Vietnamese-friendly characters, in either form
+        normalize(buffer, Normalization::Nfc).chars().all(|c| {
             c.is_ascii_alphanumeric() || c.is_ascii_whitespace() ||
             matches!(c, 'ă'|'â'|'ê'|'ô'|'ơ'|'ư'|'đ'|
                      'Ă'|'Â'|'Ê'|'Ô'|'Ơ'|'Ư'|'Đ'|
@@ -663,6 +1120,7 @@ impl LanguagePack for Vietnamese {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hip_key_core::{CandidateProvider, Engine, EngineEvent, RankingPipeline};
 
     #[test]
     fn test_vietnamese_id() {
@@ -691,9 +1149,10 @@ mod tests {
         // Basic vowels with tones
         assert_eq!(vi.convert_telex("as"), "á");
         assert_eq!(vi.convert_telex("af"), "à");
-        assert_eq!(vi.convert_telex("aj"), "ả");
-        assert_eq!(vi.convert_telex("ar"), "ạ");
-        assert_eq!(vi.convert_telex("ax"), "a");
+        assert_eq!(vi.convert_telex("ar"), "ả");
+        assert_eq!(vi.convert_telex("ax"), "ã");
+        assert_eq!(vi.convert_telex("aj"), "ạ");
+        assert_eq!(vi.convert_telex("az"), "az");
 
         assert_eq!(vi.convert_telex("es"), "é");
         assert_eq!(vi.convert_telex("is"), "í");
@@ -709,9 +1168,10 @@ mod tests {
         // ă with tones
         assert_eq!(vi.convert_telex("aws"), "ắ");
         assert_eq!(vi.convert_telex("awf"), "ằ");
-        assert_eq!(vi.convert_telex("awj"), "ẳ");
-        assert_eq!(vi.convert_telex("awr"), "ặ");
-        assert_eq!(vi.convert_telex("awx"), "ă");
+        assert_eq!(vi.convert_telex("awr"), "ẳ");
+        assert_eq!(vi.convert_telex("awx"), "ẵ");
+        assert_eq!(vi.convert_telex("awj"), "ặ");
+        assert_eq!(vi.convert_telex("awsz"), "ă");
 
         // â with tones
         assert_eq!(vi.convert_telex("aas"), "ấ");
@@ -734,21 +1194,30 @@ mod tests {
     fn test_telex_order_independent() {
         let vi = Vietnamese::with_method(InputMethod::Telex);
 
-        // as and sa should both give á
+        // A tone key may follow the whole syllable, not just the vowel
         assert_eq!(vi.convert_telex("as"), "á");
-        assert_eq!(vi.convert_telex("sa"), "á");
-
-        // aws and was should both give ắ (approximately)
+        assert_eq!(vi.convert_telex("ans"), "án");
         assert_eq!(vi.convert_telex("aws"), "ắ");
+
+        // Before any vowel it is a plain consonant: with every tone key
+        // also an onset (s, r, x), holding it for a later vowel would turn
+        // `sa`, `ra` and `xin` into `á`, `ả` and `xĩn`
+        assert_eq!(vi.convert_telex("sa"), "sa");
+        assert_eq!(vi.convert_telex("ra"), "ra");
+        assert_eq!(vi.convert_telex("xin"), "xin");
+        assert_eq!(vi.convert_telex("sas"), "sá");
+        assert_eq!(vi.convert_telex("ruwowuj"), "rượu");
+        assert_eq!(vi.convert_telex("sa"), type_keys(&vi, "sa"));
     }
 
     #[test]
     fn test_telex_remove_tone() {
         let vi = Vietnamese::with_method(InputMethod::Telex);
 
-        // x removes tone
-        assert_eq!(vi.convert_telex("asx"), "a");
-        assert_eq!(vi.convert_telex("awsx"), "ă");
+        // z removes tone
+        assert_eq!(vi.convert_telex("asz"), "a");
+        assert_eq!(vi.convert_telex("awsz"), "ă");
+        assert_eq!(vi.convert_telex("azz"), "azz");
     }
 
     #[test]
@@ -838,4 +1307,688 @@ mod tests {
         assert_eq!(vi.convert_vni("chao2"), "chào");
         assert_eq!(vi.convert_vni("u71n"), "ứn");
     }
+
+    #[test]
+    fn test_process_commit_keys() {
+        for method in [InputMethod::Telex, InputMethod::VNI] {
+            let vi = Vietnamese::with_method(method);
+
+            for key in [Key::Space, Key::Tab, Key::Enter] {
+                assert_eq!(
+                    vi.process(&Keystroke::key(key), "việt"),
+                    ProcessResult::ReadyToCommit(String::from("việt"))
+                );
+            }
+            assert_eq!(vi.process(&Keystroke::key(Key::Escape), "việt"), ProcessResult::PassThrough);
+
+            // Punctuation is committed with the word, not dropped
+            assert_eq!(
+                vi.process(&Keystroke::char(','), "việt"),
+                ProcessResult::ReadyToCommit(String::from("việt,"))
+            );
+            assert_eq!(vi.process(&Keystroke::char(','), ""), ProcessResult::PassThrough);
+        }
+    }
+
+    /// Feed `keys` through the streaming path, as the engine would
+    fn type_keys(vi: &Vietnamese, keys: &str) -> String {
+        let mut buffer = String::new();
+        let mut raw = String::new();
+        for c in keys.chars() {
+            match vi.process_with_raw(&Keystroke::char(c), &buffer, &raw) {
+                ProcessResult::Consumed => buffer.push(c),
+                ProcessResult::BufferUpdated(text) => buffer = text,
+                other => panic!("unexpected {:?} for {:?}", other, keys),
+            }
+            raw.push(c);
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_telex_double_key_undo() {
+        let vi = Vietnamese::with_method(InputMethod::Telex);
+        let cases = [
+            // Tone keys
+            ("ass", "as"),
+            ("aff", "af"),
+            ("arr", "ar"),
+            ("axx", "ax"),
+            ("ajj", "aj"),
+            // Vowel modifiers
+            ("aww", "aw"),
+            ("aaa", "aa"),
+            ("eee", "ee"),
+            ("ooo", "oo"),
+            ("oww", "ow"),
+            ("uww", "uw"),
+            ("ddd", "dd"),
+            // Inside a word, keeping the other marks
+            ("vieess", "viês"),
+            ("ddaaa", "đaa"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+            assert_eq!(vi.convert_telex(keys), expected, "bulk {}", keys);
+        }
+    }
+
+    #[test]
+    fn test_vni_double_key_undo() {
+        let vi = Vietnamese::with_method(InputMethod::VNI);
+        let cases = [
+            // Tone keys
+            ("a11", "a1"),
+            ("a22", "a2"),
+            ("a33", "a3"),
+            ("a44", "a4"),
+            ("a55", "a5"),
+            // Vowel modifiers
+            ("a88", "a8"),
+            ("a66", "a6"),
+            ("e66", "e6"),
+            ("o66", "o6"),
+            ("o77", "o7"),
+            ("u77", "u7"),
+            ("d99", "d9"),
+            // Inside a word, keeping the other marks
+            ("vie6t11", "viêt1"),
+            ("d9a66", "đa6"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+            assert_eq!(vi.convert_vni(keys), expected, "bulk {}", keys);
+        }
+    }
+
+    #[test]
+    fn test_plain_d_is_kept() {
+        let vi = Vietnamese::new();
+        assert_eq!(vi.convert_telex("da"), "da");
+        assert_eq!(type_keys(&vi, "ddis"), "đí");
+    }
+
+    #[test]
+    fn test_telex_all_tones() {
+        let vi = Vietnamese::with_method(InputMethod::Telex);
+        let cases = [
+            ("a", "a"),
+            ("as", "á"),
+            ("af", "à"),
+            ("ar", "ả"),
+            ("ax", "ã"),
+            ("aj", "ạ"),
+            ("asz", "a"),
+            ("vieetj", "việt"),
+            ("ngax", "ngã"),
+            ("hoir", "hỏi"),
+            ("nawngj", "nặng"),
+            // A later tone key replaces the earlier one
+            ("asf", "à"),
+            ("asx", "ã"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+            assert_eq!(vi.convert_telex(keys), expected, "bulk {}", keys);
+        }
+    }
+
+    #[test]
+    fn test_tone_placement_follows_syllable() {
+        let telex = Vietnamese::with_method(InputMethod::Telex);
+        let vni = Vietnamese::with_method(InputMethod::VNI);
+        let cases = [
+            ("hoas", "hoa1", "hoá"),
+            ("quar", "qua3", "quả"),
+            ("thuowr", "thuo73", "thuở"),
+            ("gifn", "gin2", "gìn"),
+            ("giaf", "gia2", "già"),
+            ("khuyur", "khuyu3", "khuỷu"),
+            ("cuar", "cua3", "của"),
+            ("ruwowuj", "ru7o7u5", "rượu"),
+            ("nguyeenx", "nguye6n4", "nguyễn"),
+            ("xinchaof", "xinchao2", "xinchào"),
+        ];
+        for (telex_keys, vni_keys, expected) in cases {
+            assert_eq!(type_keys(&telex, telex_keys), expected, "streaming {}", telex_keys);
+            assert_eq!(telex.convert_telex(telex_keys), expected, "bulk {}", telex_keys);
+            assert_eq!(type_keys(&vni, vni_keys), expected, "streaming {}", vni_keys);
+            assert_eq!(vni.convert_vni(vni_keys), expected, "bulk {}", vni_keys);
+        }
+    }
+
+    #[test]
+    fn test_tone_style() {
+        // (Telex keys, VNI keys, modern, traditional)
+        let cases = [
+            ("hoaf", "hoa2", "hoà", "hòa"),
+            ("khoer", "khoe3", "khoẻ", "khỏe"),
+            ("thuys", "thuy1", "thuý", "thúy"),
+            ("Hoaf", "Hoa2", "Hoà", "Hòa"),
+            // Closed or marked syllables are the same in both styles
+            ("hoanf", "hoan2", "hoàn", "hoàn"),
+            ("ngoaif", "ngoai2", "ngoài", "ngoài"),
+            ("hoawcj", "hoa8c5", "hoặc", "hoặc"),
+            ("thuyeenf", "thuye6n2", "thuyền", "thuyền"),
+            ("quys", "quy1", "quý", "quý"),
+        ];
+        for (telex_keys, vni_keys, modern, traditional) in cases {
+            for (style, expected) in [(ToneStyle::Modern, modern), (ToneStyle::Traditional, traditional)] {
+                let telex = Vietnamese::with_method(InputMethod::Telex).with_tone_style(style);
+                let vni = Vietnamese::with_method(InputMethod::VNI).with_tone_style(style);
+                assert_eq!(type_keys(&telex, telex_keys), expected, "{:?} streaming {}", style, telex_keys);
+                assert_eq!(telex.convert_telex(telex_keys), expected, "{:?} bulk {}", style, telex_keys);
+                assert_eq!(type_keys(&vni, vni_keys), expected, "{:?} streaming {}", style, vni_keys);
+                assert_eq!(vni.convert_vni(vni_keys), expected, "{:?} bulk {}", style, vni_keys);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tone_follows_nucleus() {
+        // (keys, Modern, Traditional)
+        let telex_cases = [
+            ("hoasn", "hoán", "hoán"),
+            ("hoafn", "hoàn", "hoàn"),
+            ("hoafi", "hoài", "hoài"),
+            ("gisa", "giá", "giá"),
+            ("tiense", "tiến", "tiến"),
+            ("muonso", "muốn", "muốn"),
+            ("thuyfeen", "thuyền", "thuyền"),
+        ];
+        let vni_cases = [
+            ("hoa1n", "hoán", "hoán"),
+            ("hoa2n", "hoàn", "hoàn"),
+            ("gi1a", "giá", "giá"),
+            ("tien16", "tiến", "tiến"),
+            ("muo1n6", "muốn", "muốn"),
+        ];
+        for (method, cases) in [(InputMethod::Telex, telex_cases.as_slice()), (InputMethod::VNI, vni_cases.as_slice())] {
+            for &(keys, modern, traditional) in cases {
+                for (style, expected) in [(ToneStyle::Modern, modern), (ToneStyle::Traditional, traditional)] {
+                    let vi = Vietnamese::with_method(method).with_tone_style(style);
+                    let bulk = if method.is_telex() { vi.convert_telex(keys) } else { vi.convert_vni(keys) };
+                    assert_eq!(type_keys(&vi, keys), expected, "{:?} streaming {}", style, keys);
+                    assert_eq!(bulk, expected, "{:?} bulk {}", style, keys);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_modifiers_anywhere_in_syllable() {
+        let telex_cases = [
+            ("tienge", "tiêng"),
+            ("tiengse", "tiếng"),
+            ("tauaf", "tầu"),
+            ("muaw", "mưa"),
+            ("muaww", "muaw"),
+            ("truongw", "trương"),
+            ("truowng", "trương"),
+            ("nguoiwf", "người"),
+            ("thuowr", "thuở"),
+            ("did", "đi"),
+            ("dead", "dead"),
+            ("hoao", "hoao"),
+            ("tooio", "toio"),
+        ];
+        let vni_cases = [
+            ("tieng6", "tiêng"),
+            ("tien6g", "tiêng"),
+            ("tieng61", "tiếng"),
+            ("tieng16", "tiếng"),
+            ("mua7", "mưa"),
+            ("truong7", "trương"),
+            ("nguoi72", "người"),
+            ("di9", "đi"),
+        ];
+        for (method, cases) in [(InputMethod::Telex, telex_cases.as_slice()), (InputMethod::VNI, vni_cases.as_slice())] {
+            let vi = Vietnamese::with_method(method);
+            for &(keys, expected) in cases {
+                let bulk = if method.is_telex() { vi.convert_telex(keys) } else { vi.convert_vni(keys) };
+                assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+                assert_eq!(bulk, expected, "bulk {}", keys);
+            }
+        }
+    }
+
+    #[test]
+    fn test_process_moves_tone() {
+        let vi = Vietnamese::new().with_tone_style(ToneStyle::Traditional);
+        assert_eq!(vi.process(&Keystroke::char('n'), "hòa"), ProcessResult::BufferUpdated(String::from("hoàn")));
+        assert_eq!(vi.process(&Keystroke::char('e'), "tién"), ProcessResult::BufferUpdated(String::from("tiến")));
+        // Nothing to move: the engine appends the key
+        assert_eq!(vi.process(&Keystroke::char('n'), "hoá"), ProcessResult::Consumed);
+    }
+
+    #[test]
+    fn test_spell_check_keeps_english() {
+        let telex = Vietnamese::with_method(InputMethod::Telex).with_spell_check(true);
+        let vni = Vietnamese::with_method(InputMethod::VNI).with_spell_check(true);
+        for word in ["class", "word", "process", "windows", "Street", "browser"] {
+            assert_eq!(type_keys(&telex, word), word);
+        }
+        assert_eq!(type_keys(&vni, "web2"), "web2");
+        assert_eq!(type_keys(&vni, "abc1"), "abc1");
+
+        // Vietnamese is still transformed, including half-typed syllables
+        let cases = [("vieejt", "việt"), ("nguwowif", "người"), ("gieengs", "giếng"), ("hoawcj", "hoặc")];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&telex, keys), expected, "{}", keys);
+        }
+        assert_eq!(type_keys(&vni, "vie6t5"), "việt");
+
+        // A tone may come before the modifier that makes the syllable valid
+        let cases = [("tiense", "tiến"), ("tiengse", "tiếng"), ("nguoiwf", "người"), ("duongwf", "dường")];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&telex, keys), expected, "{}", keys);
+        }
+        assert_eq!(type_keys(&vni, "tien16"), "tiến");
+
+        // Off by default
+        assert!(!Vietnamese::new().spell_check());
+        assert_eq!(type_keys(&Vietnamese::new(), "class"), "clas");
+    }
+
+    #[test]
+    fn test_auto_restore_on_commit() {
+        let vi = Vietnamese::with_method(InputMethod::Telex).with_auto_restore(true);
+        let commit = |keys: &str, key: Keystroke| vi.process_with_raw(&key, &type_keys(&vi, keys), keys);
+        let space = Keystroke::key(Key::Space);
+
+        // Not Vietnamese: the raw keys are committed
+        for keys in ["text", "fix", "were", "Fox", "xinchaof"] {
+            assert_eq!(commit(keys, space), ProcessResult::ReadyToCommit(keys.to_string()), "{}", keys);
+        }
+        assert_eq!(commit("text", Keystroke::char('.')), ProcessResult::ReadyToCommit(String::from("text.")));
+
+        // Vietnamese, or without marks: committed as composed
+        let cases = [("vieejt", "việt"), ("tesst", "test"), ("ddaauf", "đầu"), ("hello", "hello")];
+        for (keys, expected) in cases {
+            assert_eq!(commit(keys, space), ProcessResult::ReadyToCommit(expected.to_string()), "{}", keys);
+        }
+
+        // Off by default
+        let vi = Vietnamese::new();
+        assert_eq!(
+            vi.process_with_raw(&space, "tẽt", "text"),
+            ProcessResult::ReadyToCommit(String::from("tẽt"))
+        );
+    }
+
+    #[test]
+    fn test_quick_consonants_and_endings() {
+        let vi = Vietnamese::new().with_quick_consonants(true).with_quick_endings(true);
+        let cases = [
+            ("cc", "ch"),
+            ("ccaos", "cháo"),
+            ("ggaf", "già"),
+            ("kkoong", "không"),
+            ("nnuwowif", "người"),
+            ("ppoo", "phô"),
+            ("ttuw", "thư"),
+            ("qqas", "quá"),
+            ("Cc", "Ch"),
+            ("TT", "TH"),
+            ("vieeg", "viêng"),
+            ("mah", "manh"),
+            ("eek", "êch"),
+            ("hoasg", "hoáng"),
+            ("AG", "ANG"),
+            // Pressing again undoes and keeps the key
+            ("ccc", "cc"),
+            ("ttt", "tt"),
+            ("agg", "ag"),
+            ("ahh", "ah"),
+            // Only at the start of a word, or right after a vowel
+            ("acc", "acc"),
+            ("khong", "khong"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "{}", keys);
+        }
+
+        // Off by default, and each set on its own
+        assert_eq!(type_keys(&Vietnamese::new(), "ccag"), "ccag");
+        assert_eq!(type_keys(&Vietnamese::new().with_quick_endings(true), "ccag"), "ccang");
+        assert_eq!(type_keys(&Vietnamese::new().with_quick_consonants(true), "ccag"), "chag");
+
+        // The spelling gate still applies to endings
+        let gated = Vietnamese::new().with_quick_endings(true).with_spell_check(true);
+        assert_eq!(type_keys(&gated, "hoeg"), "hoeg");
+    }
+
+    #[test]
+    fn test_telex_shortcuts() {
+        let vi = Vietnamese::new().with_telex_shortcuts(true);
+        let cases = [
+            ("w", "ư"),
+            ("tw", "tư"),
+            ("nhwng", "nhưng"),
+            ("nhw[ng", "nhương"),
+            ("t[s", "tớ"),
+            ("t]f", "từ"),
+            ("W", "Ư"),
+            ("{", "Ơ"),
+            ("}", "Ư"),
+            // Still modifies a vowel where it can
+            ("aw", "ă"),
+            ("tuw", "tư"),
+            // Pressing again gives the key back
+            ("ww", "w"),
+            ("tww", "tw"),
+            ("[[", "["),
+            ("]]", "]"),
+            ("tuww", "tuw"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "{}", keys);
+        }
+
+        // Brackets compose instead of committing
+        assert_eq!(vi.process(&Keystroke::char('['), ""), ProcessResult::BufferUpdated(String::from("ơ")));
+        assert_eq!(
+            vi.process(&Keystroke::char('.'), "tơ"),
+            ProcessResult::ReadyToCommit(String::from("tơ."))
+        );
+
+        // Off by default, and Telex only
+        let plain = Vietnamese::new();
+        assert_eq!(type_keys(&plain, "tw"), "tw");
+        assert_eq!(plain.process(&Keystroke::char('['), "a"), ProcessResult::ReadyToCommit(String::from("a[")));
+        let vni = Vietnamese::with_method(InputMethod::VNI).with_telex_shortcuts(true);
+        assert_eq!(type_keys(&vni, "tw"), "tw");
+    }
+
+    #[test]
+    fn test_telex_variants() {
+        // (keys, Telex, Simple Telex, Telex 2)
+        let cases = [
+            ("tw", "tw", "tw", "tư"),
+            ("nhwngx", "nhwngx", "nhwngx", "những"),
+            ("tuw", "tư", "tư", "tư"),
+            ("vieejt", "việt", "việt", "việt"),
+        ];
+        for (keys, telex, simple, telex2) in cases {
+            for (method, expected) in [
+                (InputMethod::Telex, telex),
+                (InputMethod::SimpleTelex, simple),
+                (InputMethod::Telex2, telex2),
+            ] {
+                let vi = Vietnamese::with_method(method);
+                assert_eq!(type_keys(&vi, keys), expected, "{:?} {}", method, keys);
+            }
+        }
+
+        // Brackets commit as punctuation except in Telex 2
+        let bracket = |method| Vietnamese::with_method(method).process(&Keystroke::char('['), "t");
+        assert_eq!(bracket(InputMethod::SimpleTelex), ProcessResult::ReadyToCommit(String::from("t[")));
+        assert_eq!(bracket(InputMethod::Telex2), ProcessResult::BufferUpdated(String::from("tơ")));
+
+        // The shortcuts option only matters for plain Telex
+        let simple = Vietnamese::with_method(InputMethod::SimpleTelex).with_telex_shortcuts(true);
+        assert_eq!(type_keys(&simple, "tw"), "tw");
+        assert_eq!(type_keys(&Vietnamese::new().with_telex_shortcuts(true), "tw"), "tư");
+    }
+
+    #[test]
+    fn test_input_method_ids() {
+        for method in InputMethod::ALL {
+            assert_eq!(InputMethod::from_id(method.id()), Some(method));
+        }
+        assert_eq!(InputMethod::from_id("simple-telex"), Some(InputMethod::SimpleTelex));
+        assert_eq!(InputMethod::from_id("morse"), None);
+        assert_eq!(InputMethod::Telex2.name(), "Telex 2");
+    }
+
+    #[test]
+    fn test_normalization() {
+        let nfd = Vietnamese::new().with_normalization(Normalization::Nfd);
+        assert_eq!(type_keys(&nfd, "vieejt"), "vie\u{0323}\u{0302}t");
+        assert_eq!(type_keys(&nfd, "nguwowif"), "ngu\u{031B}o\u{031B}\u{0300}i");
+        assert_eq!(
+            nfd.process(&Keystroke::key(Key::Space), "vie\u{0323}\u{0302}t"),
+            ProcessResult::ReadyToCommit(String::from("vie\u{0323}\u{0302}t"))
+        );
+
+        // Decomposed text in the buffer is read as the letters it spells
+        let vi = Vietnamese::new();
+        assert_eq!(vi.process(&Keystroke::char('j'), "vie\u{0302}t"), ProcessResult::BufferUpdated(String::from("việt")));
+        assert_eq!(vi.process(&Keystroke::char('e'), "vie\u{0301}t"), ProcessResult::BufferUpdated(String::from("viết")));
+        assert!(vi.is_valid_composition("vie\u{0323}\u{0302}t"));
+        assert!(Syllable::parse("vie\u{0323}\u{0302}t").is_some_and(|syllable| syllable.is_valid()));
+    }
+
+    #[test]
+    fn test_generate_candidates() {
+        let vi = Vietnamese::new();
+        let candidates = vi.generate_candidates("vie");
+        let texts: Vec<&str> = candidates.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts[0], "việt");
+        for word in ["viên", "viện", "Việt Nam"] {
+            assert!(texts.contains(&word), "{}", word);
+        }
+        // Ranked, with confidences that share out the matches
+        assert!(candidates.windows(2).all(|pair| pair[0].confidence >= pair[1].confidence));
+        assert!(candidates.iter().map(|c| c.confidence).sum::<f32>() <= 1.0 + f32::EPSILON);
+        assert!(candidates.len() <= MAX_CANDIDATES);
+        let kind = |text: &str| candidates.iter().find(|c| c.text == text).map(|c| c.kind);
+        assert_eq!((kind("việt"), kind("Việt Nam")), (Some(CandidateKind::Word), Some(CandidateKind::Phrase)));
+
+        // Case follows what was typed
+        assert_eq!(vi.generate_candidates("Vie")[0].text, "Việt");
+        assert_eq!(vi.generate_candidates("VIE")[0].text, "VIỆT");
+        assert!(vi.generate_candidates("").is_empty());
+
+        let nfd = Vietnamese::new().with_normalization(Normalization::Nfd);
+        assert_eq!(nfd.generate_candidates("việ")[0].text, "vie\u{0323}\u{0302}t");
+    }
+
+    #[test]
+    fn test_accentless_input() {
+        let vi = Vietnamese::with_method(InputMethod::Accentless);
+        let texts = |result: ProcessResult| match result {
+            ProcessResult::Candidates(candidates) => candidates.into_iter().map(|c| c.text).collect::<Vec<_>>(),
+            other => panic!("unexpected {:?}", other),
+        };
+
+        // Letters stay as typed and come back as candidates
+        assert_eq!(texts(vi.process(&Keystroke::char('t'), "vie"))[..3], ["việt", "Việt Nam", "viết"]);
+        assert_eq!(texts(vi.process(&Keystroke::char('T'), "Vie"))[0], "Việt");
+        // Space keeps composing a phrase
+        let space = Keystroke::key(Key::Space);
+        assert_eq!(texts(vi.process(&space, "viet"))[0], "việt");
+        assert_eq!(texts(vi.process(&Keystroke::char('m'), "viet na"))[0], "Việt Nam");
+        assert_eq!(texts(vi.process(&Keystroke::char('m'), "toi yeu viet na"))[0], "tôi yêu Việt Nam");
+
+        // A second Space or punctuation commits the letters as typed, as
+        // does Enter when the engine passes it on (no candidates shown)
+        assert_eq!(vi.process(&space, "viet "), ProcessResult::ReadyToCommit(String::from("viet")));
+        assert_eq!(vi.process(&Keystroke::char('.'), "viet "), ProcessResult::ReadyToCommit(String::from("viet.")));
+        assert_eq!(vi.process(&Keystroke::key(Key::Enter), "viet"), ProcessResult::ReadyToCommit(String::from("viet")));
+        assert_eq!(vi.process(&Keystroke::char('.'), ""), ProcessResult::PassThrough);
+
+        // Without readings, the first Space already commits
+        assert_eq!(vi.process(&space, "xyz"), ProcessResult::ReadyToCommit(String::from("xyz")));
+        assert_eq!(vi.process(&Keystroke::char(' '), "xyz"), ProcessResult::ReadyToCommit(String::from("xyz ")));
+
+        assert_eq!(vi.generate_candidates("viet nam")[0].text, "Việt Nam");
+        let nfd = Vietnamese::with_method(InputMethod::Accentless).with_normalization(Normalization::Nfd);
+        assert_eq!(texts(nfd.process(&Keystroke::char('t'), "vie"))[0], "vie\u{0323}\u{0302}t");
+    }
+
+    #[test]
+    fn test_accentless_in_engine() {
+        let finish = |keys: &str, key: Key| {
+            let mut engine = Engine::new();
+            engine.set_language_pack(Box::new(Vietnamese::with_method(InputMethod::Accentless)));
+            for c in keys.chars() {
+                let _ = engine.process(&Keystroke::char(c));
+            }
+            engine.process(&Keystroke::key(key))
+        };
+
+        // Enter selects the highlighted candidate, Escape keeps the letters
+        assert_eq!(finish("viet", Key::Enter), [EngineEvent::Commit(String::from("việt")), EngineEvent::BufferChanged]);
+        assert_eq!(
+            finish("viet", Key::Escape),
+            [EngineEvent::Commit(String::from("viet")), EngineEvent::BufferChanged]
+        );
+        // With nothing to select, Enter commits the letters and passes through
+        assert_eq!(
+            finish("xyz", Key::Enter),
+            [EngineEvent::Commit(String::from("xyz")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+    }
+
+    /// Provider suggesting the same words whatever is typed
+    struct FixedProvider;
+
+    impl CandidateProvider for FixedProvider {
+        fn candidates(&self, _composing: &str) -> CandidateList {
+            vec![Candidate::new("viết"), Candidate::new("bank")]
+        }
+    }
+
+    #[test]
+    fn test_provider_candidates_in_engine() {
+        let engine = |method, selection_keys| {
+            let mut engine = Engine::new();
+            engine.set_ranking(RankingPipeline::new().with_provider(FixedProvider));
+            engine.set_selection_keys(selection_keys);
+            engine.set_language_pack(Box::new(Vietnamese::with_method(method)));
+            engine
+        };
+        let type_in = |engine: &mut Engine, keys: &str| {
+            keys.chars().map(|c| engine.process(&Keystroke::char(c))).last().unwrap_or_default()
+        };
+
+        // Shown, but keys still reach the pack
+        for (method, keys, expected) in [
+            (InputMethod::VNI, "vie1", "víe"),
+            (InputMethod::VNI, "vi1", "ví"),
+            (InputMethod::Telex, "ban1", "ban1"),
+            (InputMethod::Telex, "vieets", "viết"),
+        ] {
+            let mut engine = engine(method, false);
+            let _ = type_in(&mut engine, keys);
+            assert_eq!(engine.buffer().composing(), expected, "{}", keys);
+            assert_eq!(engine.candidates().len(), 2);
+        }
+        let mut telex = engine(InputMethod::Telex, false);
+        let _ = type_in(&mut telex, "ban");
+        assert_eq!(
+            telex.process(&Keystroke::key(Key::Enter)),
+            [EngineEvent::Commit(String::from("ban")), EngineEvent::BufferChanged, EngineEvent::PassThrough]
+        );
+
+        // With selection keys on, tone digits still go to the pack
+        let mut vni = engine(InputMethod::VNI, true);
+        let _ = type_in(&mut vni, "vie1");
+        assert_eq!(vni.buffer().composing(), "víe");
+        let mut telex = engine(InputMethod::Telex, true);
+        assert_eq!(
+            type_in(&mut telex, "ban2"),
+            [EngineEvent::Commit(String::from("bank")), EngineEvent::BufferChanged]
+        );
+        let _ = type_in(&mut telex, "ban");
+        assert_eq!(
+            telex.process(&Keystroke::key(Key::Enter)),
+            [EngineEvent::Commit(String::from("viết")), EngineEvent::BufferChanged]
+        );
+    }
+
+    #[test]
+    fn test_viqr_input() {
+        let vi = Vietnamese::with_method(InputMethod::Viqr);
+        let cases = [
+            ("a(", "ă"),
+            ("a^", "â"),
+            ("e^", "ê"),
+            ("o^", "ô"),
+            ("o+", "ơ"),
+            ("u+", "ư"),
+            ("dd", "đ"),
+            ("d-", "đ"),
+            ("a'", "á"),
+            ("a`", "à"),
+            ("a?", "ả"),
+            ("a~", "ã"),
+            ("a.", "ạ"),
+            ("vie^.t", "việt"),
+            ("VIE^.T", "VIỆT"),
+            ("ddu+o+.c", "được"),
+            ("hoa`", "hoà"),
+            ("hoa`n", "hoàn"),
+            // Repeating a mark undoes it and keeps it
+            ("a''", "a'"),
+            ("a^^", "a^"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "{}", keys);
+        }
+
+        // Marks away from a letter they fit are punctuation
+        assert_eq!(vi.process(&Keystroke::char('?'), "không"), ProcessResult::ReadyToCommit(String::from("không?")));
+        assert_eq!(vi.process(&Keystroke::char('.'), ""), ProcessResult::PassThrough);
+        // A backslash keeps the mark literal
+        assert_eq!(vi.process(&Keystroke::char('\\'), "ba"), ProcessResult::Consumed);
+        assert_eq!(vi.process(&Keystroke::char('?'), "ba\\"), ProcessResult::BufferUpdated(String::from("ba?")));
+    }
+
+    #[test]
+    fn test_telex_case_preserved() {
+        let vi = Vietnamese::with_method(InputMethod::Telex);
+        let cases = [
+            ("AW", "Ă"),
+            ("Aw", "Ă"),
+            ("aW", "ă"),
+            ("DD", "Đ"),
+            ("Dd", "Đ"),
+            ("OO", "Ô"),
+            ("UWS", "Ứ"),
+            ("VIEEJT", "VIỆT"),
+            ("Vieejt", "Việt"),
+            ("ddaaua", "đaua"),
+            ("DDaauf", "Đầu"),
+            ("NAWMF", "NẰM"),
+            // Undo keeps the case of each key
+            ("AWW", "AW"),
+            ("ASs", "As"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+            assert_eq!(vi.convert_telex(keys), expected, "bulk {}", keys);
+        }
+    }
+
+    #[test]
+    fn test_vni_case_preserved() {
+        let vi = Vietnamese::with_method(InputMethod::VNI);
+        let cases = [
+            ("A8", "Ă"),
+            ("D9", "Đ"),
+            ("VIE6T5", "VIỆT"),
+            ("Vie6t5", "Việt"),
+            ("U71", "Ứ"),
+        ];
+        for (keys, expected) in cases {
+            assert_eq!(type_keys(&vi, keys), expected, "streaming {}", keys);
+            assert_eq!(vi.convert_vni(keys), expected, "bulk {}", keys);
+        }
+    }
+
+    #[test]
+    fn test_process_respects_shift() {
+        let vi = Vietnamese::with_method(InputMethod::Telex);
+        let shifted = |c| Keystroke {
+            key: Key::Char(c),
+            modifiers: hip_key_core::Modifiers { shift: true, ..Default::default() },
+        };
+        assert_eq!(vi.process(&shifted('w'), "A"), ProcessResult::BufferUpdated(String::from("Ă")));
+        // The modified letter keeps its own case
+        assert_eq!(vi.process(&shifted('d'), "d"), ProcessResult::BufferUpdated(String::from("đ")));
+        // Shift on a tone key doesn't change what it does
+        assert_eq!(vi.process(&shifted('s'), "Ă"), ProcessResult::BufferUpdated(String::from("Ắ")));
+    }
 }
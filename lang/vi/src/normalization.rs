@@ -0,0 +1,149 @@
+//! Unicode normalization of Vietnamese letters
+//!
+//! A Vietnamese letter can be written precomposed (NFC: `ệ` is U+1EC7) or
+//! as its base letter followed by combining diacritics (NFD: `e`, U+0323,
+//! U+0302). The pack composes in NFC, reads either form, and emits the one
+//! it is set to.
+
+use crate::{CharInfo, ToneMark, VowelMod, VOWELS};
+
+/// Unicode normalization form of the text the pack emits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Precomposed letters (`ệ`), what most applications expect
+    #[default]
+    Nfc,
+    /// Base letters with combining diacritics, as in macOS file names
+    Nfd,
+}
+
+/// Combining diacritic for a vowel modification (đ has none)
+fn modification_mark(vowel_mod: VowelMod) -> Option<char> {
+    match vowel_mod {
+        VowelMod::Breve => Some('\u{0306}'),
+        VowelMod::Circumflex => Some('\u{0302}'),
+        VowelMod::Horn => Some('\u{031B}'),
+        VowelMod::None | VowelMod::Stroke => None,
+    }
+}
+
+/// Combining diacritic for a tone
+fn tone_mark(tone: ToneMark) -> Option<char> {
+    match tone {
+        ToneMark::None => None,
+        ToneMark::Acute => Some('\u{0301}'),
+        ToneMark::Grave => Some('\u{0300}'),
+        ToneMark::HookAbove => Some('\u{0309}'),
+        ToneMark::Tilde => Some('\u{0303}'),
+        ToneMark::DotBelow => Some('\u{0323}'),
+    }
+}
+
+/// Add the combining diacritic `c` to `ch`, if it is one that fits
+///
+/// Returns `false`, leaving `ch` alone, for anything else.
+pub(crate) fn combine(ch: &mut CharInfo, c: char) -> bool {
+    let modification = [VowelMod::Breve, VowelMod::Circumflex, VowelMod::Horn]
+        .into_iter()
+        .find(|&vowel_mod| modification_mark(vowel_mod) == Some(c));
+    if let Some(vowel_mod) = modification {
+        let exists = VOWELS.iter().any(|&(base, m, _)| base == ch.base && m == vowel_mod);
+        if exists && ch.vowel_mod == VowelMod::None {
+            ch.vowel_mod = vowel_mod;
+            return true;
+        }
+        return false;
+    }
+    match ToneMark::ALL.into_iter().find(|&tone| tone_mark(tone) == Some(c)) {
+        Some(tone) if ch.can_take_tone && ch.tone == ToneMark::None => {
+            ch.tone = tone;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Write the letter `ch` to `out` in `form`
+fn push(out: &mut String, ch: &CharInfo, form: Normalization) {
+    // đ has no decomposition
+    if form == Normalization::Nfc || ch.vowel_mod == VowelMod::Stroke {
+        out.push(ch.with_tone(ch.tone));
+        return;
+    }
+    out.push(CharInfo { vowel_mod: VowelMod::None, ..ch.clone() }.with_tone(ToneMark::None));
+    let (modification, tone) = (modification_mark(ch.vowel_mod), tone_mark(ch.tone));
+    // Canonical order: horn, then dot below, then the marks above
+    if ch.tone == ToneMark::DotBelow && ch.vowel_mod != VowelMod::Horn {
+        out.extend(tone.into_iter().chain(modification));
+    } else {
+        out.extend(modification.into_iter().chain(tone));
+    }
+}
+
+/// Write the Vietnamese letters of `text` in `form`
+///
+/// Both forms are read, so this converts either way. Other characters,
+/// and combining marks that don't belong to a Vietnamese letter, are left
+/// as they are.
+pub fn normalize(text: &str, form: Normalization) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut letter: Option<CharInfo> = None;
+    for c in text.chars() {
+        if letter.as_mut().is_some_and(|ch| combine(ch, c)) {
+            continue;
+        }
+        if let Some(ch) = letter.take() {
+            push(&mut out, &ch, form);
+        }
+        let ch = CharInfo::new(c);
+        let vietnamese = ch.can_take_tone || ch.vowel_mod == VowelMod::Stroke;
+        if vietnamese && ch.with_tone(ch.tone) == c {
+            letter = Some(ch);
+        } else {
+            out.push(c);
+        }
+    }
+    if let Some(ch) = letter {
+        push(&mut out, &ch, form);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfd() {
+        let cases = [
+            ("ệ", "e\u{0323}\u{0302}"),
+            ("ấ", "a\u{0302}\u{0301}"),
+            ("ợ", "o\u{031B}\u{0323}"),
+            ("Ặ", "A\u{0323}\u{0306}"),
+            ("đường", "đu\u{031B}o\u{031B}\u{0300}ng"),
+        ];
+        for (nfc, nfd) in cases {
+            assert_eq!(normalize(nfc, Normalization::Nfd), nfd, "{}", nfc);
+            assert_eq!(normalize(nfd, Normalization::Nfc), nfc, "{}", nfc);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lower: String = VOWELS.iter().flat_map(|(_, _, forms)| forms).collect();
+        let text = format!("{} {} Tiếng Việt, naïve İ ñ", lower, lower.to_uppercase());
+        let nfd = normalize(&text, Normalization::Nfd);
+        assert!(nfd.chars().count() > text.chars().count());
+        assert_eq!(normalize(&nfd, Normalization::Nfc), text);
+        assert_eq!(normalize(&text, Normalization::Nfc), text);
+    }
+
+    #[test]
+    fn test_mixed_forms() {
+        // Partly composed input, as after `ạ` + a combining circumflex
+        assert_eq!(normalize("a\u{0323}\u{0302}", Normalization::Nfc), "ậ");
+        assert_eq!(normalize("ạ\u{0302}", Normalization::Nfc), "ậ");
+        // Marks that don't make a Vietnamese letter are kept
+        assert_eq!(normalize("n\u{0303} ă\u{0302}", Normalization::Nfc), "n\u{0303} ă\u{0302}");
+    }
+}
@@ -0,0 +1,488 @@
+//! Vietnamese syllable structure
+//!
+//! A syllable is split into initial consonant, glide, nucleus, coda and
+//! tone. Tone placement and the input method transformations work on this
+//! split rather than scanning characters.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{CharInfo, ToneMark, VowelMod};
+
+/// Initial consonants, spelled in lowercase without `qu` and `gi`
+const INITIALS: [&str; 26] = [
+    "b", "c", "ch", "d", "đ", "g", "gh", "h", "k", "kh", "l", "m", "n", "ng", "ngh", "nh", "p",
+    "ph", "q", "r", "s", "t", "th", "tr", "v", "x",
+];
+
+/// Rhymes: glide and nucleus, then every coda it takes (`""` for none)
+const RHYMES: [(&str, &[&str]); 30] = [
+    ("a", &["", "c", "ch", "i", "m", "n", "ng", "nh", "o", "p", "t", "u", "y"]),
+    ("ă", &["c", "m", "n", "ng", "p", "t"]),
+    ("â", &["c", "m", "n", "ng", "p", "t", "u", "y"]),
+    ("e", &["", "c", "m", "n", "ng", "o", "p", "t"]),
+    ("ê", &["", "ch", "m", "n", "nh", "p", "t", "u"]),
+    ("i", &["", "ch", "m", "n", "nh", "p", "t", "u"]),
+    ("ia", &[""]),
+    ("iê", &["c", "m", "n", "ng", "p", "t", "u"]),
+    ("yê", &["m", "n", "t", "u"]),
+    ("y", &[""]),
+    ("o", &["", "c", "i", "m", "n", "ng", "p", "t"]),
+    ("oo", &["c", "ng"]),
+    ("ô", &["", "c", "i", "m", "n", "ng", "p", "t"]),
+    ("ơ", &["", "i", "m", "n", "p", "t"]),
+    ("u", &["", "c", "i", "m", "n", "ng", "p", "t"]),
+    ("ua", &[""]),
+    ("uô", &["c", "i", "m", "n", "ng", "p", "t"]),
+    ("ư", &["", "c", "i", "m", "n", "ng", "t", "u"]),
+    ("ưa", &[""]),
+    ("ươ", &["c", "i", "m", "n", "ng", "p", "t", "u"]),
+    ("oa", &["", "c", "ch", "i", "m", "n", "ng", "nh", "p", "t", "y"]),
+    ("oă", &["c", "m", "n", "ng", "t"]),
+    ("oe", &["", "n", "o", "t"]),
+    ("uâ", &["n", "ng", "t", "y"]),
+    ("uê", &["", "ch", "n", "nh"]),
+    ("uy", &["", "ch", "n", "nh", "p", "t", "u"]),
+    ("uyê", &["n", "t"]),
+    ("uya", &[""]),
+    ("uơ", &[""]),
+    ("ya", &[""]),
+];
+
+/// Where the tone mark goes on `oa`, `oe` and `uy` in open syllables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneStyle {
+    /// On the main vowel: hoà, hoè, thuý
+    #[default]
+    Modern,
+    /// On the glide: hòa, hòe, thúy
+    Traditional,
+}
+
+/// Length of each part of a syllable, in characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    initial: usize,
+    glide: usize,
+    nucleus: usize,
+    coda: usize,
+}
+
+impl Layout {
+    /// Split `chars` into syllable parts, or `None` if they don't form one
+    fn of(chars: &[CharInfo]) -> Option<Layout> {
+        let is_vowel = |i: usize| chars.get(i).is_some_and(|ch| ch.can_take_tone);
+        let mut initial = chars.iter().take_while(|ch| !ch.can_take_tone).count();
+
+        // `qu` and `gi` are onsets when another vowel follows: quả, già
+        if initial == 1 && is_vowel(2) && chars[1].vowel_mod == VowelMod::None {
+            if let ('q', 'u') | ('g', 'i') = (chars[0].base, chars[1].base) {
+                initial = 2;
+            }
+        }
+
+        let vowels = chars[initial..].iter().take_while(|ch| ch.can_take_tone).count();
+        if vowels == 0 || chars[initial + vowels..].iter().any(|ch| ch.can_take_tone) {
+            return None;
+        }
+        let consonants = chars.len() - initial - vowels;
+
+        let run = &chars[initial..initial + vowels];
+        let glide = usize::from(run.len() >= 2 && is_glide(&run[0], &run[1]));
+        let rest = &run[glide..];
+        let offglide = usize::from(consonants == 0 && rest.len() >= 2 && is_offglide(&rest[rest.len() - 1]));
+
+        Some(Layout {
+            initial,
+            glide,
+            nucleus: rest.len() - offglide,
+            coda: offglide + consonants,
+        })
+    }
+
+    fn initial(&self) -> Range<usize> {
+        0..self.initial
+    }
+
+    fn glide(&self) -> Range<usize> {
+        let start = self.initial().end;
+        start..start + self.glide
+    }
+
+    fn nucleus(&self) -> Range<usize> {
+        let start = self.glide().end;
+        start..start + self.nucleus
+    }
+
+    fn coda(&self) -> Range<usize> {
+        let start = self.nucleus().end;
+        start..start + self.coda
+    }
+
+    /// Index of the vowel that carries the tone mark
+    fn tone_position(&self, chars: &[CharInfo], style: ToneStyle) -> usize {
+        let nucleus = self.nucleus();
+        if style == ToneStyle::Traditional
+            && self.glide == 1
+            && self.nucleus == 1
+            && self.coda == 0
+            && chars[nucleus.start].vowel_mod == VowelMod::None
+        {
+            return self.glide().start;
+        }
+        // A marked vowel always takes it: viết, thuở, rượu
+        if let Some(i) = nucleus.clone().rev().find(|&i| chars[i].vowel_mod != VowelMod::None) {
+            return i;
+        }
+        // Open ia/ua/ya put it first (mía, của); otherwise the last vowel
+        if nucleus.len() >= 2 && self.coda == 0 {
+            nucleus.start
+        } else {
+            nucleus.end - 1
+        }
+    }
+}
+
+/// Lowercase spelling of `chars` without tone marks
+pub(crate) fn spell(chars: &[CharInfo]) -> String {
+    chars
+        .iter()
+        .flat_map(|ch| ch.with_tone(ToneMark::None).to_lowercase())
+        .collect()
+}
+
+/// Whether `rhyme` (glide and nucleus) followed by `coda` is a Vietnamese rhyme
+///
+/// With `complete` unset, a coda that more letters could finish also counts.
+fn is_rhyme(rhyme: &str, coda: &str, complete: bool) -> bool {
+    RHYMES.iter().any(|&(r, codas)| {
+        r == rhyme && codas.iter().any(|&c| if complete { c == coda } else { c.starts_with(coda) })
+    })
+}
+
+/// Whether `first` is the medial `o`/`u` before `second` (hoa, hoe, tuần, thuở, thuỷ)
+fn is_glide(first: &CharInfo, second: &CharInfo) -> bool {
+    first.vowel_mod == VowelMod::None
+        && matches!(
+            (first.base, second.base, second.vowel_mod),
+            ('o', 'a', VowelMod::None | VowelMod::Breve)
+                | ('o', 'e', VowelMod::None)
+                | ('u', 'y', _)
+                | ('u', 'a' | 'e', VowelMod::Circumflex)
+                | ('u', 'o', VowelMod::Horn)
+        )
+}
+
+/// Whether `last` is a semivowel ending (hai, cháo, yêu) rather than part of the nucleus
+fn is_offglide(last: &CharInfo) -> bool {
+    last.vowel_mod == VowelMod::None && matches!(last.base, 'i' | 'y' | 'o' | 'u')
+}
+
+/// A Vietnamese syllable split into its parts
+///
+/// Parts are rendered without their tone mark and keep the case they were
+/// typed in: `Quyển` has initial `Qu`, nucleus `yê`, coda `n` and a hỏi tone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syllable {
+    chars: Vec<CharInfo>,
+    layout: Layout,
+}
+
+impl Syllable {
+    /// Parse a single syllable, or `None` if `text` isn't shaped like one
+    ///
+    /// Precomposed and decomposed (NFD) text are both read.
+    pub fn parse(text: &str) -> Option<Self> {
+        let chars = CharInfo::parse(text);
+        if !chars.iter().all(|ch| ch.base.is_alphabetic()) {
+            return None;
+        }
+        Self::from_chars(chars)
+    }
+
+    /// Build a syllable from parsed characters
+    pub(crate) fn from_chars(chars: Vec<CharInfo>) -> Option<Self> {
+        let layout = Layout::of(&chars)?;
+        Some(Self { chars, layout })
+    }
+
+    /// Index in `chars` where the last syllable starts
+    ///
+    /// Composing text may hold several syllables run together (`xinchao`);
+    /// transformations apply to the one being typed.
+    pub(crate) fn start_of_last(chars: &[CharInfo]) -> Option<usize> {
+        let last_vowel = chars.iter().rposition(|ch| ch.can_take_tone)?;
+        let vowels_start = chars[..last_vowel]
+            .iter()
+            .rposition(|ch| !ch.can_take_tone)
+            .map_or(0, |i| i + 1);
+        let Some(previous_vowel) = chars[..vowels_start].iter().rposition(|ch| ch.can_take_tone) else {
+            return Some(0);
+        };
+        // Between two syllables, the longest initial belongs to the second
+        let start = (previous_vowel + 1..vowels_start)
+            .find(|&i| INITIALS.contains(&spell(&chars[i..vowels_start]).as_str()))
+            .unwrap_or(vowels_start);
+        Some(start)
+    }
+
+    /// The last syllable of `chars` and where it starts
+    pub(crate) fn last_of(chars: &[CharInfo]) -> Option<(usize, Syllable)> {
+        let start = Self::start_of_last(chars)?;
+        Self::from_chars(chars[start..].to_vec()).map(|syllable| (start, syllable))
+    }
+
+    fn part(&self, range: Range<usize>) -> String {
+        self.chars[range].iter().map(|ch| ch.with_tone(ToneMark::None)).collect()
+    }
+
+    /// Initial consonant, including `qu` and `gi` (empty for `anh`)
+    pub fn initial(&self) -> String {
+        self.part(self.layout.initial())
+    }
+
+    /// Medial `o` or `u` (`hoa`, `tuần`), empty if there is none
+    pub fn glide(&self) -> String {
+        self.part(self.layout.glide())
+    }
+
+    /// Main vowel or diphthong
+    pub fn nucleus(&self) -> String {
+        self.part(self.layout.nucleus())
+    }
+
+    /// Final consonant or semivowel ending (`ng`, `i` in `hai`), possibly empty
+    pub fn coda(&self) -> String {
+        self.part(self.layout.coda())
+    }
+
+    /// Tone currently marked anywhere in the syllable
+    pub fn tone(&self) -> ToneMark {
+        self.chars
+            .iter()
+            .map(|ch| ch.tone)
+            .find(|&t| t != ToneMark::None)
+            .unwrap_or(ToneMark::None)
+    }
+
+    /// Character index of the vowel that should carry the tone mark
+    pub fn tone_position(&self, style: ToneStyle) -> usize {
+        self.layout.tone_position(&self.chars, style)
+    }
+
+    /// Whether this is a Vietnamese syllable by the onset and rhyme tables
+    ///
+    /// Spelling rules are checked too (`ca` but `ke`, `ghi`, `nghe`), and a
+    /// `c`, `ch`, `p` or `t` ending only takes sắc or nặng. A syllable
+    /// without a tone yet is judged by its letters alone.
+    pub fn is_valid(&self) -> bool {
+        self.check(true)
+    }
+
+    /// Whether typing more final consonants could still make this valid
+    ///
+    /// `viê` and `hoă` are not syllables yet, but `viêt` and `hoăc` are.
+    pub(crate) fn is_valid_prefix(&self) -> bool {
+        self.check(false)
+    }
+
+    fn check(&self, complete: bool) -> bool {
+        let initial = spell(&self.chars[self.layout.initial()]);
+        let mut rhyme = spell(&self.chars[self.layout.glide().start..self.layout.nucleus().end]);
+        let coda = spell(&self.chars[self.layout.coda()]);
+
+        let initial_ok = match initial.as_str() {
+            "" => true,
+            // The onset already holds the glide: qua, quyên, quốc
+            "qu" => !rhyme.starts_with(['u', 'ư', 'o']) || rhyme.starts_with("ô"),
+            // giêng is spelled for gi + iêng
+            "gi" => {
+                let absorbed = rhyme.starts_with('ê');
+                if absorbed {
+                    rhyme.insert(0, 'i');
+                }
+                absorbed || !rhyme.starts_with('i')
+            }
+            other => INITIALS.contains(&other) && other != "q",
+        };
+        let front = rhyme.starts_with(['e', 'ê', 'i', 'y']);
+        let spelling_ok = match initial.as_str() {
+            "c" | "ng" => !front,
+            "g" => !rhyme.starts_with(['e', 'ê', 'y']),
+            "k" | "gh" | "ngh" => front,
+            _ => true,
+        };
+        let tone_ok = !matches!(coda.as_str(), "c" | "ch" | "p" | "t")
+            || matches!(self.tone(), ToneMark::None | ToneMark::Acute | ToneMark::DotBelow);
+
+        initial_ok && spelling_ok && tone_ok && is_rhyme(&rhyme, &coda, complete)
+    }
+
+    /// Remove any tone mark and put `tone` on the right vowel for `style`
+    pub fn set_tone(&mut self, tone: ToneMark, style: ToneStyle) {
+        let pos = self.tone_position(style);
+        for ch in self.chars.iter_mut() {
+            ch.tone = ToneMark::None;
+        }
+        self.chars[pos].tone = tone;
+    }
+
+    /// Put the horn on both halves of `ươ` once a final follows
+    ///
+    /// Open `uơ` stands alone (`thuở`), but with a final only `ươ` is
+    /// spelled, so `truơng` and `trưong` both become `trương`.
+    pub(crate) fn complete_horns(&mut self) {
+        if self.layout.coda == 0 {
+            return;
+        }
+        let vowels = self.layout.glide().start..self.layout.nucleus().end;
+        if let [first, second] = &mut self.chars[vowels] {
+            let mods = (first.vowel_mod, second.vowel_mod);
+            if (first.base, second.base) == ('u', 'o')
+                && matches!(mods, (VowelMod::None, VowelMod::Horn) | (VowelMod::Horn, VowelMod::None))
+            {
+                first.vowel_mod = VowelMod::Horn;
+                second.vowel_mod = VowelMod::Horn;
+            }
+        }
+        if let Some(layout) = Layout::of(&self.chars) {
+            self.layout = layout;
+        }
+    }
+
+    pub(crate) fn into_chars(self) -> Vec<CharInfo> {
+        self.chars
+    }
+}
+
+impl fmt::Display for Syllable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&CharInfo::render(&self.chars))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(text: &str) -> (String, String, String, String, ToneMark) {
+        let s = Syllable::parse(text).unwrap();
+        (s.initial(), s.glide(), s.nucleus(), s.coda(), s.tone())
+    }
+
+    #[test]
+    fn test_parse_parts() {
+        let cases = [
+            ("a", ("", "", "a", "", ToneMark::None)),
+            ("nghiêng", ("ngh", "", "iê", "ng", ToneMark::None)),
+            ("hoà", ("h", "o", "a", "", ToneMark::Grave)),
+            ("quả", ("qu", "", "a", "", ToneMark::HookAbove)),
+            ("già", ("gi", "", "a", "", ToneMark::Grave)),
+            ("gìn", ("g", "", "i", "n", ToneMark::Grave)),
+            ("thuở", ("th", "u", "ơ", "", ToneMark::HookAbove)),
+            ("khuya", ("kh", "u", "ya", "", ToneMark::None)),
+            ("rượu", ("r", "", "ươ", "u", ToneMark::DotBelow)),
+            ("Quyển", ("Qu", "", "yê", "n", ToneMark::HookAbove)),
+            ("ngoài", ("ng", "o", "a", "i", ToneMark::Grave)),
+        ];
+        for (text, (initial, glide, nucleus, coda, tone)) in cases {
+            assert_eq!(
+                parts(text),
+                (initial.into(), glide.into(), nucleus.into(), coda.into(), tone),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_non_syllables() {
+        assert!(Syllable::parse("").is_none());
+        assert!(Syllable::parse("nh").is_none());
+        assert!(Syllable::parse("hello").is_none());
+        assert!(Syllable::parse("a1").is_none());
+    }
+
+    #[test]
+    fn test_set_tone_position() {
+        let cases = [
+            ("hoa", ToneMark::Acute, "hoá"),
+            ("qua", ToneMark::HookAbove, "quả"),
+            ("gin", ToneMark::Grave, "gìn"),
+            ("thuơ", ToneMark::HookAbove, "thuở"),
+            ("khuyu", ToneMark::HookAbove, "khuỷu"),
+            ("mua", ToneMark::Grave, "mùa"),
+            ("mia", ToneMark::Acute, "mía"),
+            ("tuân", ToneMark::Grave, "tuần"),
+            ("nguyên", ToneMark::Tilde, "nguyễn"),
+            ("chao", ToneMark::Grave, "chào"),
+            ("hóa", ToneMark::Acute, "hoá"),
+        ];
+        for (text, tone, expected) in cases {
+            let mut s = Syllable::parse(text).unwrap();
+            s.set_tone(tone, ToneStyle::Modern);
+            assert_eq!(s.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_traditional_style_marks_glide() {
+        let cases = [
+            ("hoa", "hòa"),
+            ("hoe", "hòe"),
+            ("thuy", "thùy"),
+            ("ngoai", "ngoài"),
+            ("hoan", "hoàn"),
+            ("hoăc", "hoằc"),
+            ("thuyên", "thuyền"),
+        ];
+        for (text, expected) in cases {
+            let mut s = Syllable::parse(text).unwrap();
+            s.set_tone(ToneMark::Grave, ToneStyle::Traditional);
+            assert_eq!(s.to_string(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_is_valid() {
+        let valid = [
+            "a", "ăn", "việt", "nghiêng", "quả", "quốc", "già", "giếng", "gìn", "khuya", "khuỷu",
+            "rượu", "hoàng", "thuở", "ngoằn", "kể", "ghi", "nghe", "xoong", "yêu", "Tiếng",
+        ];
+        for text in valid {
+            assert!(Syllable::parse(text).unwrap().is_valid(), "{}", text);
+        }
+        let invalid = [
+            "clá", "wỏ", "cé", "ka", "ghá", "ngé", "viêch", "hoec", "aun", "ăi", "qa", "bềt", "viê",
+        ];
+        for text in invalid {
+            assert!(!Syllable::parse(text).unwrap().is_valid(), "{}", text);
+        }
+
+        // Still typing: a final consonant can complete these
+        for text in ["viê", "hoă", "giê", "thuyê"] {
+            assert!(Syllable::parse(text).unwrap().is_valid_prefix(), "{}", text);
+        }
+        for text in ["clá", "ăi", "bềt"] {
+            assert!(!Syllable::parse(text).unwrap().is_valid_prefix(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_last_of_run_together_syllables() {
+        let chars = CharInfo::parse("xinchao");
+        let (start, syllable) = Syllable::last_of(&chars).unwrap();
+        assert_eq!(start, 3);
+        assert_eq!(syllable.to_string(), "chao");
+    }
+
+    #[test]
+    fn test_complete_horns() {
+        let cases = [("truơng", "trương"), ("trưong", "trương"), ("nguơi", "ngươi"), ("thuơ", "thuơ"), ("uông", "uông")];
+        for (text, expected) in cases {
+            let mut syllable = Syllable::parse(text).unwrap();
+            syllable.complete_horns();
+            assert_eq!(syllable.to_string(), expected, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! VIQR text conversion
+//!
+//! VIQR writes Vietnamese in plain ASCII with each mark right after its
+//! letter: `(` breve, `^` circumflex, `+` horn, `dd` for đ, and the tones
+//! `'` `` ` `` `?` `~` `.`. A backslash keeps the next character literal.
+
+use crate::{viqr_tone, viqr_vowel_mod, CharInfo, ToneMark, VowelMod};
+
+/// Whether `key` is read as a mark on `prev` when decoding
+///
+/// A letter takes one modification, then one tone: `a^'` is `ấ`, but the
+/// `.` in `la`.` stays punctuation.
+fn is_mark_on(prev: &CharInfo, key: char) -> bool {
+    let unmarked = prev.tone == ToneMark::None;
+    let modifies = prev.vowel_mod == VowelMod::None && viqr_vowel_mod(prev.base, key).is_some();
+    let tones = prev.can_take_tone && viqr_tone(key).is_some();
+    unmarked && (modifies || tones)
+}
+
+/// VIQR mark for a vowel modification (đ is written `dd` instead)
+fn vowel_mod_mark(vowel_mod: VowelMod) -> Option<char> {
+    match vowel_mod {
+        VowelMod::Breve => Some('('),
+        VowelMod::Circumflex => Some('^'),
+        VowelMod::Horn => Some('+'),
+        VowelMod::None | VowelMod::Stroke => None,
+    }
+}
+
+/// VIQR mark for a tone
+fn tone_mark(tone: ToneMark) -> Option<char> {
+    match tone {
+        ToneMark::None => None,
+        ToneMark::Acute => Some('\''),
+        ToneMark::Grave => Some('`'),
+        ToneMark::HookAbove => Some('?'),
+        ToneMark::Tilde => Some('~'),
+        ToneMark::DotBelow => Some('.'),
+    }
+}
+
+/// Convert VIQR text to Unicode Vietnamese (`Vie^.t Nam` → `Việt Nam`)
+pub fn to_unicode(text: &str) -> String {
+    let mut chars: Vec<CharInfo> = Vec::new();
+    let mut input = text.chars();
+    while let Some(c) = input.next() {
+        if c == '\\' {
+            // Escaped: the next character is literal
+            if let Some(literal) = input.next() {
+                chars.push(CharInfo::new(literal));
+            }
+            continue;
+        }
+        match chars.last_mut() {
+            Some(prev) if is_mark_on(prev, c) => {
+                if let Some(vowel_mod) = viqr_vowel_mod(prev.base, c) {
+                    prev.vowel_mod = vowel_mod;
+                } else if let Some(tone) = viqr_tone(c) {
+                    prev.tone = tone;
+                }
+            }
+            _ => chars.push(CharInfo::new(c)),
+        }
+    }
+    CharInfo::render(&chars)
+}
+
+/// Convert Unicode Vietnamese to VIQR text (`Việt Nam` → `Vie^.t Nam`)
+///
+/// Characters that would otherwise be read as marks are escaped, so
+/// `to_unicode(&from_unicode(text))` gives `text` back.
+pub fn from_unicode(text: &str) -> String {
+    let mut out = String::new();
+    let mut prev: Option<CharInfo> = None;
+    for c in text.chars() {
+        let ch = CharInfo::new(c);
+        if c == '\\' || prev.as_ref().is_some_and(|prev| is_mark_on(prev, c)) {
+            out.push('\\');
+        }
+        if ch.vowel_mod == VowelMod::Stroke {
+            let d = if ch.upper { 'D' } else { 'd' };
+            out.extend([d, d]);
+        } else if ch.can_take_tone {
+            out.push(CharInfo { vowel_mod: VowelMod::None, ..ch.clone() }.with_tone(ToneMark::None));
+            out.extend(vowel_mod_mark(ch.vowel_mod));
+            out.extend(tone_mark(ch.tone));
+        } else {
+            out.push(c);
+        }
+        prev = Some(ch);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: [(&str, &str); 6] = [
+        ("Vie^.t Nam", "Việt Nam"),
+        ("DDa^y la` tie^'ng Vie^.t.", "Đây là tiếng Việt."),
+        ("Ba.n co' khoe? kho^ng?", "Bạn có khoẻ không?"),
+        ("Tru+o+`ng ho.c", "Trường học"),
+        ("a( a^ e^ o^ o+ u+ dd", "ă â ê ô ơ ư đ"),
+        ("NGU+O+`I", "NGƯỜI"),
+    ];
+
+    #[test]
+    fn test_to_unicode() {
+        for (viqr, unicode) in PAIRS {
+            assert_eq!(to_unicode(viqr), unicode, "{}", viqr);
+        }
+        // Alternative spelling of đ
+        assert_eq!(to_unicode("d-i"), "đi");
+    }
+
+    #[test]
+    fn test_from_unicode() {
+        for (viqr, unicode) in PAIRS {
+            assert_eq!(from_unicode(unicode), viqr, "{}", unicode);
+        }
+    }
+
+    #[test]
+    fn test_escapes() {
+        // Punctuation right after a bare vowel is escaped
+        assert_eq!(from_unicode("Ba."), "Ba\\.");
+        assert_eq!(from_unicode("Why?"), "Why\\?");
+        assert_eq!(from_unicode("add"), "ad\\d");
+        assert_eq!(from_unicode("C:\\a"), "C:\\\\a");
+        assert_eq!(to_unicode("Ba\\."), "Ba.");
+        assert_eq!(to_unicode("ad\\d"), "add");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let texts = [
+            "Hà Nội, ngày 2 tháng 9.",
+            "Gõ tiếng Việt (VIQR) dễ không?",
+            "address: a+b=c; e^x",
+            "ĐƯỢC Đà Nẵng \\ path",
+        ];
+        for text in texts {
+            assert_eq!(to_unicode(&from_unicode(text)), text, "{}", text);
+        }
+    }
+}